@@ -0,0 +1,578 @@
+//! Which keys and mouse buttons do what, kept in a file so they can be
+//! changed.
+//!
+//! The file is plain text: a header line, then one binding per line, as
+//! `action = trigger`. A trigger is a key (like `n`, `space` or `f1`) or a
+//! mouse button (`left_click`, `middle_click`, `right_click`, or
+//! `both_clicks` for left and right at once), after any of `ctrl+`, `alt+`
+//! and `shift+`. An action can have any number of bindings.
+
+use std::io;
+
+use minifb::{Key, KeyRepeat, MouseButton, Window, MENU_KEY_ALT, MENU_KEY_CTRL, MENU_KEY_SHIFT};
+
+use crate::shared::{self, Lang};
+
+const FILE_NAME: &str = "bindings.txt";
+const HEADER: &str = "minesweeper bindings 1";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    Open,
+    Flag,
+    Chord,
+    CursorUp,
+    CursorDown,
+    CursorLeft,
+    CursorRight,
+    ScrollUp,
+    ScrollDown,
+    ScrollLeft,
+    ScrollRight,
+    /// Closes whichever window is open.
+    CloseWindow,
+    NewGame,
+    Quit,
+    Undo,
+    Redo,
+    Hint,
+    ShowOdds,
+    Save,
+    Load,
+    BoardCode,
+    Records,
+    SaveReplay,
+    ExportRawvf,
+    KeyboardHelp,
+    SpeedNormal,
+    SpeedDouble,
+    SpeedInstant,
+}
+
+/// Every action, with its name in the file and its names for people.
+pub const ACTIONS: &[(Action, &str, &str, &str)] = &[
+    (Action::Open, "open", "Open", "開く"),
+    (Action::Flag, "flag", "Flag", "旗"),
+    (Action::Chord, "chord", "Chord", "まとめて開く"),
+    (Action::CursorUp, "cursor_up", "Cursor up", "カーソル上"),
+    (
+        Action::CursorDown,
+        "cursor_down",
+        "Cursor down",
+        "カーソル下",
+    ),
+    (
+        Action::CursorLeft,
+        "cursor_left",
+        "Cursor left",
+        "カーソル左",
+    ),
+    (
+        Action::CursorRight,
+        "cursor_right",
+        "Cursor right",
+        "カーソル右",
+    ),
+    (Action::ScrollUp, "scroll_up", "Scroll up", "上へスクロール"),
+    (
+        Action::ScrollDown,
+        "scroll_down",
+        "Scroll down",
+        "下へスクロール",
+    ),
+    (
+        Action::ScrollLeft,
+        "scroll_left",
+        "Scroll left",
+        "左へスクロール",
+    ),
+    (
+        Action::ScrollRight,
+        "scroll_right",
+        "Scroll right",
+        "右へスクロール",
+    ),
+    (
+        Action::CloseWindow,
+        "close_window",
+        "Close window",
+        "ウィンドウを閉じる",
+    ),
+    (Action::NewGame, "new_game", "New game", "新しいゲーム"),
+    (Action::Quit, "quit", "Quit", "ゲームをやめる"),
+    (Action::Undo, "undo", "Undo", "元に戻す"),
+    (Action::Redo, "redo", "Redo", "やり直す"),
+    (Action::Hint, "hint", "Hint", "ヒント"),
+    (
+        Action::ShowOdds,
+        "show_odds",
+        "Show mine odds",
+        "地雷の確率",
+    ),
+    (Action::Save, "save", "Save", "保存"),
+    (Action::Load, "load", "Load", "読み込む"),
+    (Action::BoardCode, "board_code", "Board code", "盤面コード"),
+    (Action::Records, "records", "Records", "記録"),
+    (
+        Action::SaveReplay,
+        "save_replay",
+        "Save replay",
+        "リプレイを保存",
+    ),
+    (
+        Action::ExportRawvf,
+        "export_rawvf",
+        "Export RAWVF",
+        "RAWVFをエクスポート",
+    ),
+    (
+        Action::KeyboardHelp,
+        "keyboard_help",
+        "Keyboard help",
+        "キーボード操作",
+    ),
+    (
+        Action::SpeedNormal,
+        "speed_normal",
+        "Replay 1x",
+        "リプレイ1x",
+    ),
+    (
+        Action::SpeedDouble,
+        "speed_double",
+        "Replay 2x",
+        "リプレイ2x",
+    ),
+    (
+        Action::SpeedInstant,
+        "speed_instant",
+        "Replay instantly",
+        "リプレイ一瞬",
+    ),
+];
+
+impl Action {
+    pub fn name(self, lang: Lang) -> &'static str {
+        let &(_, _, en, jp) = ACTIONS.iter().find(|a| a.0 == self).unwrap();
+        lang.en_jp(en, jp)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Trigger {
+    Key(Key),
+    Mouse(MouseButton),
+    /// Left and right released together.
+    BothButtons,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Binding {
+    pub action: Action,
+    pub trigger: Trigger,
+    /// `MENU_KEY_CTRL` and such, since the menus use them too.
+    pub mods: usize,
+}
+
+pub struct Bindings {
+    pub list: Vec<Binding>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        const DEFAULTS: &str = "\
+            open = left_click\n\
+            open = space\n\
+            open = enter\n\
+            open = numpadenter\n\
+            flag = right_click\n\
+            flag = ctrl+left_click\n\
+            flag = f\n\
+            chord = middle_click\n\
+            chord = both_clicks\n\
+            chord = shift+left_click\n\
+            chord = d\n\
+            cursor_up = up\n\
+            cursor_up = k\n\
+            cursor_down = down\n\
+            cursor_down = j\n\
+            cursor_left = left\n\
+            cursor_left = h\n\
+            cursor_right = right\n\
+            cursor_right = l\n\
+            scroll_up = shift+up\n\
+            scroll_up = pageup\n\
+            scroll_down = shift+down\n\
+            scroll_down = pagedown\n\
+            scroll_left = shift+left\n\
+            scroll_right = shift+right\n\
+            close_window = escape\n\
+            new_game = ctrl+n\n\
+            quit = alt+f4\n\
+            undo = ctrl+z\n\
+            redo = ctrl+y\n\
+            hint = ctrl+h\n\
+            show_odds = ctrl+p\n\
+            save = ctrl+s\n\
+            load = ctrl+o\n\
+            board_code = ctrl+b\n\
+            records = ctrl+r\n\
+            save_replay = ctrl+e\n\
+            export_rawvf = ctrl+shift+e\n\
+            keyboard_help = f1\n\
+            speed_normal = 1\n\
+            speed_double = 2\n\
+            speed_instant = 3\n";
+        Self::parse(&format!("{HEADER}\n{DEFAULTS}")).expect("the defaults are valid")
+    }
+}
+
+impl Bindings {
+    /// The defaults with the left and right mouse buttons swapped.
+    pub fn left_handed() -> Self {
+        let mut bindings = Self::default();
+        for binding in &mut bindings.list {
+            binding.trigger = match binding.trigger {
+                Trigger::Mouse(MouseButton::Left) => Trigger::Mouse(MouseButton::Right),
+                Trigger::Mouse(MouseButton::Right) => Trigger::Mouse(MouseButton::Left),
+                trigger => trigger,
+            };
+        }
+        bindings
+    }
+
+    /// Loads the bindings, or the defaults if they can't be read.
+    pub fn load() -> Self {
+        shared::read_data_file(FILE_NAME)
+            .and_then(|s| s.map_or_else(|| Ok(Self::default()), |s| Self::parse(&s)))
+            .unwrap_or_else(|err| {
+                eprintln!("Couldn't read the bindings: {err}");
+                Self::default()
+            })
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        shared::write_data_file(FILE_NAME, &self.serialize())
+    }
+
+    /// The action for a click with these modifiers held. Modifiers that
+    /// nothing uses with the button are ignored.
+    pub fn mouse_action(&self, trigger: Trigger, mods: usize) -> Option<Action> {
+        let find = |mods| {
+            self.list
+                .iter()
+                .find(|b| b.trigger == trigger && b.mods == mods)
+                .map(|b| b.action)
+        };
+        find(mods).or_else(|| find(0))
+    }
+
+    /// The first of `actions` with a key that was just pressed, with exactly
+    /// its modifiers held.
+    pub fn pressed(&self, window: &Window, actions: &[Action]) -> Option<Action> {
+        let mods = mods_down(window);
+        self.list
+            .iter()
+            .filter(|b| actions.contains(&b.action) && b.mods == mods)
+            .find(|b| match b.trigger {
+                Trigger::Key(key) => {
+                    // Holding the cursor and scroll keys keeps moving.
+                    let repeat = match b.action {
+                        Action::CursorUp
+                        | Action::CursorDown
+                        | Action::CursorLeft
+                        | Action::CursorRight
+                        | Action::ScrollUp
+                        | Action::ScrollDown
+                        | Action::ScrollLeft
+                        | Action::ScrollRight => KeyRepeat::Yes,
+                        _ => KeyRepeat::No,
+                    };
+                    window.is_key_pressed(key, repeat)
+                }
+                _ => false,
+            })
+            .map(|b| b.action)
+    }
+
+    /// Whether one of the action's keys is held down, whatever the modifiers.
+    pub fn is_down(&self, window: &Window, action: Action) -> bool {
+        self.list.iter().any(|b| {
+            b.action == action && matches!(b.trigger, Trigger::Key(key) if window.is_key_down(key))
+        })
+    }
+
+    /// The first key for an action, for showing in menus.
+    pub fn shortcut(&self, action: Action) -> Option<(Key, usize)> {
+        self.list.iter().find_map(|b| match b.trigger {
+            Trigger::Key(key) if b.action == action => Some((key, b.mods)),
+            _ => None,
+        })
+    }
+
+    /// Lists an action's bindings, like `space, enter`.
+    pub fn describe(&self, action: Action) -> String {
+        let names: Vec<String> = self
+            .list
+            .iter()
+            .filter(|b| b.action == action)
+            .map(binding_text)
+            .collect();
+        if names.is_empty() {
+            "-".to_owned()
+        } else {
+            names.join(", ")
+        }
+    }
+
+    /// Replaces the action's keys with `key`, keeping its mouse buttons.
+    pub fn set_key(&mut self, action: Action, key: Key, mods: usize) {
+        self.list
+            .retain(|b| b.action != action || !matches!(b.trigger, Trigger::Key(_)));
+        self.list.push(Binding {
+            action,
+            trigger: Trigger::Key(key),
+            mods,
+        });
+    }
+
+    fn serialize(&self) -> String {
+        let mut s = format!("{HEADER}\n");
+        for binding in &self.list {
+            let &(_, name, _, _) = ACTIONS.iter().find(|a| a.0 == binding.action).unwrap();
+            s += &format!("{name} = {}\n", binding_text(binding));
+        }
+        s
+    }
+
+    fn parse(s: &str) -> io::Result<Self> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+        let mut lines = s.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(invalid("not a bindings file".to_owned()));
+        }
+        let mut list = Vec::new();
+        for line in lines.filter(|line| !line.trim().is_empty()) {
+            let bad_line = || invalid(format!("bad line '{line}'"));
+            let (name, trigger) = line.split_once('=').ok_or_else(bad_line)?;
+            let &(action, _, _, _) = ACTIONS
+                .iter()
+                .find(|a| a.1 == name.trim())
+                .ok_or_else(|| invalid(format!("unknown action in '{line}'")))?;
+
+            let mut mods = 0;
+            let mut parts: Vec<&str> = trigger.trim().split('+').collect();
+            let last = parts.pop().ok_or_else(bad_line)?;
+            for part in parts {
+                mods |= match part {
+                    "ctrl" => MENU_KEY_CTRL,
+                    "alt" => MENU_KEY_ALT,
+                    "shift" => MENU_KEY_SHIFT,
+                    _ => return Err(invalid(format!("unknown modifier in '{line}'"))),
+                };
+            }
+            let trigger = match last {
+                "left_click" => Trigger::Mouse(MouseButton::Left),
+                "middle_click" => Trigger::Mouse(MouseButton::Middle),
+                "right_click" => Trigger::Mouse(MouseButton::Right),
+                "both_clicks" => Trigger::BothButtons,
+                _ => Trigger::Key(
+                    KEYS.iter()
+                        .copied()
+                        .find(|&key| key_name(key) == last)
+                        .ok_or_else(|| invalid(format!("unknown key in '{line}'")))?,
+                ),
+            };
+            list.push(Binding {
+                action,
+                trigger,
+                mods,
+            });
+        }
+        Ok(Self { list })
+    }
+}
+
+/// The modifiers held down right now.
+pub fn mods_down(window: &Window) -> usize {
+    let is_down = |left, right| window.is_key_down(left) || window.is_key_down(right);
+    let mut mods = 0;
+    if is_down(Key::LeftCtrl, Key::RightCtrl) {
+        mods |= MENU_KEY_CTRL;
+    }
+    if is_down(Key::LeftAlt, Key::RightAlt) {
+        mods |= MENU_KEY_ALT;
+    }
+    if is_down(Key::LeftShift, Key::RightShift) {
+        mods |= MENU_KEY_SHIFT;
+    }
+    mods
+}
+
+/// A binding as it's written in the file, without the action.
+pub fn binding_text(binding: &Binding) -> String {
+    let mut s = String::new();
+    for (flag, name) in [
+        (MENU_KEY_CTRL, "ctrl+"),
+        (MENU_KEY_ALT, "alt+"),
+        (MENU_KEY_SHIFT, "shift+"),
+    ] {
+        if binding.mods & flag != 0 {
+            s += name;
+        }
+    }
+    s += &match binding.trigger {
+        Trigger::Key(key) => key_name(key),
+        Trigger::Mouse(MouseButton::Left) => "left_click".to_owned(),
+        Trigger::Mouse(MouseButton::Middle) => "middle_click".to_owned(),
+        Trigger::Mouse(MouseButton::Right) => "right_click".to_owned(),
+        Trigger::BothButtons => "both_clicks".to_owned(),
+    };
+    s
+}
+
+/// Like `Key`'s names, in lowercase, but with plain digits.
+fn key_name(key: Key) -> String {
+    let name = format!("{key:?}").to_lowercase();
+    match name.strip_prefix("key") {
+        Some(digit) => digit.to_owned(),
+        None => name,
+    }
+}
+
+/// The keys that can be bound. Modifiers can't, on their own.
+pub const KEYS: &[Key] = {
+    use Key::*;
+    &[
+        Key0,
+        Key1,
+        Key2,
+        Key3,
+        Key4,
+        Key5,
+        Key6,
+        Key7,
+        Key8,
+        Key9,
+        A,
+        B,
+        C,
+        D,
+        E,
+        F,
+        G,
+        H,
+        I,
+        J,
+        K,
+        L,
+        M,
+        N,
+        O,
+        P,
+        Q,
+        R,
+        S,
+        T,
+        U,
+        V,
+        W,
+        X,
+        Y,
+        Z,
+        F1,
+        F2,
+        F3,
+        F4,
+        F5,
+        F6,
+        F7,
+        F8,
+        F9,
+        F10,
+        F11,
+        F12,
+        Down,
+        Left,
+        Right,
+        Up,
+        Apostrophe,
+        Backquote,
+        Backslash,
+        Comma,
+        Equal,
+        LeftBracket,
+        Minus,
+        Period,
+        RightBracket,
+        Semicolon,
+        Slash,
+        Backspace,
+        Delete,
+        End,
+        Enter,
+        Escape,
+        Home,
+        Insert,
+        PageDown,
+        PageUp,
+        Space,
+        Tab,
+        NumPad0,
+        NumPad1,
+        NumPad2,
+        NumPad3,
+        NumPad4,
+        NumPad5,
+        NumPad6,
+        NumPad7,
+        NumPad8,
+        NumPad9,
+        NumPadDot,
+        NumPadSlash,
+        NumPadAsterisk,
+        NumPadMinus,
+        NumPadPlus,
+        NumPadEnter,
+    ]
+};
+
+#[test]
+fn test_bindings() {
+    let bindings = Bindings::default();
+    let left = Trigger::Mouse(MouseButton::Left);
+    assert_eq!(bindings.mouse_action(left, 0), Some(Action::Open));
+    assert_eq!(
+        bindings.mouse_action(left, MENU_KEY_CTRL),
+        Some(Action::Flag)
+    );
+    // Alt doesn't do anything special, so it's a normal click.
+    assert_eq!(
+        bindings.mouse_action(left, MENU_KEY_ALT),
+        Some(Action::Open)
+    );
+    assert_eq!(
+        bindings.shortcut(Action::ExportRawvf),
+        Some((Key::E, MENU_KEY_CTRL | MENU_KEY_SHIFT))
+    );
+    assert_eq!(bindings.describe(Action::CursorUp), "up, k");
+
+    let left_handed = Bindings::left_handed();
+    assert_eq!(left_handed.mouse_action(left, 0), Some(Action::Flag));
+    assert_eq!(
+        left_handed.mouse_action(Trigger::Mouse(MouseButton::Right), MENU_KEY_SHIFT),
+        Some(Action::Chord)
+    );
+
+    let mut changed = Bindings::default();
+    changed.set_key(Action::Flag, Key::G, MENU_KEY_ALT);
+    assert_eq!(
+        changed.describe(Action::Flag),
+        "right_click, ctrl+left_click, alt+g"
+    );
+    let loaded = Bindings::parse(&changed.serialize()).unwrap();
+    assert_eq!(loaded.list, changed.list);
+
+    assert!(Bindings::parse(&format!("{HEADER}\nopen = hyper+x\n")).is_err());
+    assert!(Bindings::parse(&format!("{HEADER}\nfly = x\n")).is_err());
+}
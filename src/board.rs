@@ -0,0 +1,962 @@
+//! The game rules, independent of any window, so games can be driven from
+//! tests, bots, or other frontends.
+
+// It complains about `&Vec<Vec<_>>`. I'm not incurring the complexity of making
+// them _and_ their contents generic.
+#![allow(clippy::ptr_arg)]
+
+use crate::board_code;
+use crate::shared::{self, Config};
+use crate::solver;
+
+/// The number of cells that *must* be free of mines at the start of the game.
+pub const SAFE_CELLS_FOR_FIRST_CLICK: usize = 9; // 1 + 8 surrounding cells
+
+/// How many layouts to try before settling for one that needs a guess.
+const NO_GUESS_ATTEMPTS: usize = 1000;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(u8)]
+pub enum Cell {
+    Unopened,
+    Opened,
+    Flagged,
+    /// Marked as maybe a mine. Only used if the board has question marks on.
+    Questioned,
+}
+
+impl Cell {
+    /// Whether opening the cell does anything. Flags protect cells from being
+    /// opened, but question marks don't.
+    pub fn can_open(self) -> bool {
+        matches!(self, Cell::Unopened | Cell::Questioned)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum GameState {
+    #[default]
+    InProgress,
+    Won,
+    Lost,
+}
+
+#[derive(Clone)]
+pub struct Board {
+    cols: usize,
+    rows: usize,
+    mine_count: usize,
+    /// What `rng` started from, so the game can be played again. Boards with
+    /// fixed layouts don't have one.
+    seed: Option<u64>,
+    rng: fastrand::Rng,
+
+    /// Whether each cell has a mine. Gets initialized on first click so we can ensure the player doesn't immediately lose.
+    mines: Box<[bool]>,
+    mine_counts: Box<[u8]>,
+    cells: Vec<Vec<Cell>>,
+    mines_placed: bool,
+    /// Whether a cell has been opened yet, which starts the clock. Not part
+    /// of `Change`: undoing doesn't stop the clock.
+    started: bool,
+    /// Whether mines may be moved out from under the player's first unlucky click.
+    anti_guess: bool,
+    /// Whether to only generate layouts that can be cleared without guessing.
+    no_guess: bool,
+    /// Whether no-guess mode gave up, so the layout might need a guess anyway.
+    no_guess_failed: bool,
+    /// Whether unflagging a cell marks it with a question mark first.
+    question_marks: bool,
+
+    move_count: usize,
+    /// Every click on the board, even ones undone later. Not part of `Change`.
+    clicks: Clicks,
+    state: GameState,
+
+    /// Moves that can be undone, oldest first.
+    history: Vec<Change>,
+    /// Moves that were undone and can be redone, most recently undone last.
+    undone: Vec<Change>,
+    /// The move being made right now.
+    pending: Change,
+    undo_count: usize,
+    /// Goes up with every move, undo and redo.
+    changes: usize,
+}
+
+/// How many times the player clicked on the board.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Clicks {
+    pub total: usize,
+    /// Only the ones that changed something.
+    pub effective: usize,
+}
+
+/// Everything a move changed, so it can be undone (or redone). Holds the
+/// *other* version of everything: undoing or redoing swaps it with the board.
+#[derive(Clone, Default)]
+struct Change {
+    cells: Vec<(usize, usize, Cell)>,
+    /// Only present if the mines moved.
+    mines: Option<Box<[bool]>>,
+    mines_placed: bool,
+    move_count: usize,
+    state: GameState,
+}
+
+impl Board {
+    /// Creates a board from `cfg.seed`, or a random seed if there isn't one.
+    /// The same seed, size, mine count and first click always give the same
+    /// layout.
+    pub fn new(cfg: &Config) -> Self {
+        // Random seeds are kept short enough to be easy to share.
+        let seed = cfg.seed.unwrap_or_else(|| fastrand::u32(..).into());
+        Self {
+            cols: cfg.cell_cols,
+            rows: cfg.cell_rows,
+            mine_count: cfg.mine_count,
+            seed: Some(seed),
+            rng: fastrand::Rng::with_seed(seed),
+            mines: vec![false; cfg.cell_rows * cfg.cell_cols].into_boxed_slice(),
+            mine_counts: vec![0; cfg.cell_rows * cfg.cell_cols].into_boxed_slice(),
+            cells: vec![vec![Cell::Unopened; cfg.cell_cols]; cfg.cell_rows],
+            mines_placed: false,
+            started: false,
+            // Moving mines around could ruin a no-guess layout.
+            anti_guess: !cfg.no_guess,
+            no_guess: cfg.no_guess,
+            no_guess_failed: false,
+            question_marks: cfg.question_marks,
+            move_count: 0,
+            clicks: Clicks::default(),
+            state: GameState::InProgress,
+            history: Vec::new(),
+            undone: Vec::new(),
+            pending: Change::default(),
+            undo_count: 0,
+            changes: 0,
+        }
+    }
+
+    /// Creates a board with a known mine layout (in row-major order). The
+    /// layout is kept exactly as given, so mines are never moved.
+    pub fn with_mines(cols: usize, rows: usize, mines: Box<[bool]>) -> Self {
+        assert_eq!(mines.len(), cols * rows, "wrong number of cells");
+        let mut board = Self {
+            cols,
+            rows,
+            mine_count: mines.iter().filter(|&&b| b).count(),
+            seed: None,
+            rng: fastrand::Rng::new(),
+            mines,
+            mine_counts: vec![0; cols * rows].into_boxed_slice(),
+            cells: vec![vec![Cell::Unopened; cols]; rows],
+            mines_placed: true,
+            started: false,
+            anti_guess: false,
+            no_guess: false,
+            no_guess_failed: false,
+            question_marks: false,
+            move_count: 0,
+            clicks: Clicks::default(),
+            state: GameState::InProgress,
+            history: Vec::new(),
+            undone: Vec::new(),
+            pending: Change::default(),
+            undo_count: 0,
+            changes: 0,
+        };
+        board.generate_mine_counts();
+        board
+    }
+
+    /// Rebuilds a game from saved parts. `mines` is `None` if they haven't
+    /// been placed yet.
+    pub fn restore(
+        cfg: &Config,
+        mines: Option<Box<[bool]>>,
+        cells: Vec<Vec<Cell>>,
+        started: bool,
+        move_count: usize,
+        undo_count: usize,
+        clicks: Clicks,
+    ) -> Self {
+        let mut board = Self::new(cfg);
+        if cfg.seed.is_none() {
+            // Only fixed layouts (see `with_mines`) are saved without seeds.
+            board.seed = None;
+            board.anti_guess = false;
+        }
+        assert_eq!(cells.len(), board.rows, "wrong number of rows");
+        if let Some(mines) = mines {
+            assert_eq!(mines.len(), board.mines.len(), "wrong number of cells");
+            board.mines = mines;
+            board.mines_placed = true;
+            board.generate_mine_counts();
+        }
+        board.cells = cells;
+        board.started = started;
+        board.move_count = move_count;
+        board.undo_count = undo_count;
+        board.clicks = clicks;
+        let opened_a_mine = (0..board.rows)
+            .flat_map(|y| (0..board.cols).map(move |x| (x, y)))
+            .any(|(x, y)| board.cells[y][x] == Cell::Opened && board.is_mine(x, y));
+        board.update_state(opened_a_mine);
+        board
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+    pub fn mine_count(&self) -> usize {
+        self.mine_count
+    }
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+    pub fn mines_placed(&self) -> bool {
+        self.mines_placed
+    }
+    /// Whether the board was meant to need no guessing, but no layout like
+    /// that turned up, so the player should be told it might.
+    pub fn may_need_guess(&self) -> bool {
+        self.no_guess_failed
+    }
+    /// Whether the player has opened a cell yet. Fixed layouts have their
+    /// mines from the start, so this is what starts the clock.
+    pub fn has_started(&self) -> bool {
+        self.started
+    }
+    pub fn move_count(&self) -> usize {
+        self.move_count
+    }
+    pub fn clicks(&self) -> Clicks {
+        self.clicks
+    }
+    pub fn question_marks(&self) -> bool {
+        self.question_marks
+    }
+    /// Turns question marks on or off. Cells already marked stay marked.
+    pub fn set_question_marks(&mut self, question_marks: bool) {
+        self.question_marks = question_marks;
+    }
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+    pub fn is_game_over(&self) -> bool {
+        self.state != GameState::InProgress
+    }
+    /// How many times a move was undone.
+    pub fn undo_count(&self) -> usize {
+        self.undo_count
+    }
+    /// Counts every change to the board, so anything worked out from it can
+    /// tell when it's out of date.
+    pub fn changes(&self) -> usize {
+        self.changes
+    }
+
+    pub fn cell(&self, x: usize, y: usize) -> Cell {
+        self.cells[y][x]
+    }
+    pub fn cells(&self) -> &Vec<Vec<Cell>> {
+        &self.cells
+    }
+    pub fn is_mine(&self, x: usize, y: usize) -> bool {
+        self.mines[self.idx(x, y)]
+    }
+    /// The number of mines around the cell. Only meaningful to the player once
+    /// the cell is opened.
+    pub fn nearby_mines(&self, x: usize, y: usize) -> u8 {
+        self.mine_counts[self.idx(x, y)]
+    }
+
+    /// The mine count minus the number of flags. Goes negative if the player
+    /// places too many flags.
+    pub fn mines_left(&self) -> isize {
+        let flags = self
+            .cells
+            .iter()
+            .flatten()
+            .filter(|&&c| c == Cell::Flagged)
+            .count();
+        self.mine_count as isize - flags as isize
+    }
+
+    #[inline]
+    fn idx(&self, x: usize, y: usize) -> usize {
+        y * self.cols + x
+    }
+
+    /// Opens an unopened cell, placing the mines first if this is the first move.
+    /// Returns whether anything happened.
+    pub fn open(&mut self, x: usize, y: usize) -> bool {
+        if self.is_game_over() {
+            return false;
+        }
+        self.clicks.total += 1;
+        if !self.cells[y][x].can_open() {
+            return false;
+        }
+        self.clicks.effective += 1;
+        self.started = true;
+        self.begin_move();
+
+        if !self.mines_placed {
+            self.save_mines_for_undo();
+            if self.no_guess {
+                self.initialize_no_guess_mines((x, y));
+            } else {
+                self.initialize_mines((x, y));
+                self.generate_mine_counts();
+            }
+            self.mines_placed = true;
+        }
+
+        self.open_cell(x, y);
+
+        self.move_count += 1;
+        self.update_state(self.mines[self.idx(x, y)]);
+        self.end_move();
+        true
+    }
+
+    /// Chording/multi-open/whatever-you-want-to-call-it: if there are enough
+    /// flags, open the cells all around. Returns whether there were the right
+    /// number of flags for that.
+    pub fn chord(&mut self, x: usize, y: usize) -> bool {
+        if self.is_game_over() {
+            return false;
+        }
+        self.clicks.total += 1;
+        if self.cells[y][x] != Cell::Opened {
+            return false;
+        }
+
+        let mut flag_count = 0;
+        do_surrounding(self.cols, self.rows, x, y, |sx, sy| {
+            if self.cells[sy][sx] == Cell::Flagged {
+                flag_count += 1;
+            }
+        });
+        if flag_count != self.mine_counts[self.idx(x, y)] {
+            return false;
+        }
+        self.clicks.effective += 1;
+        self.started = true;
+
+        self.begin_move();
+        let mut to_open = Vec::new();
+        do_surrounding(self.cols, self.rows, x, y, |sx, sy| {
+            if self.cells[sy][sx].can_open() {
+                to_open.push((sx, sy));
+            }
+        });
+        let mut opened_any_mines = false;
+        for (sx, sy) in to_open {
+            self.open_cell(sx, sy);
+            if self.mines[self.idx(sx, sy)] {
+                opened_any_mines = true;
+            }
+        }
+
+        self.move_count += 1;
+        self.update_state(opened_any_mines);
+        self.end_move();
+        true
+    }
+
+    /// Flags an unopened cell or unflags a flagged one, with a question mark in
+    /// between if they're on. Returns whether anything happened.
+    pub fn toggle_flag(&mut self, x: usize, y: usize) -> bool {
+        if self.is_game_over() {
+            return false;
+        }
+        self.clicks.total += 1;
+        let new_cell = match self.cells[y][x] {
+            Cell::Unopened => Cell::Flagged,
+            Cell::Opened => return false,
+            Cell::Flagged if self.question_marks => Cell::Questioned,
+            Cell::Flagged | Cell::Questioned => Cell::Unopened,
+        };
+        self.clicks.effective += 1;
+        self.begin_move();
+        self.set_cell(x, y, new_cell);
+        self.end_move();
+        true
+    }
+
+    /// Takes back the last move, even one that lost the game. Returns whether
+    /// there was a move to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(mut change) = self.history.pop() else {
+            return false;
+        };
+        self.swap_change(&mut change);
+        self.undone.push(change);
+        self.undo_count += 1;
+        self.changes += 1;
+        true
+    }
+
+    /// Makes the last undone move again. Returns whether there was one.
+    pub fn redo(&mut self) -> bool {
+        let Some(mut change) = self.undone.pop() else {
+            return false;
+        };
+        self.swap_change(&mut change);
+        self.history.push(change);
+        self.changes += 1;
+        true
+    }
+
+    fn begin_move(&mut self) {
+        self.pending = Change {
+            cells: Vec::new(),
+            mines: None,
+            mines_placed: self.mines_placed,
+            move_count: self.move_count,
+            state: self.state,
+        };
+    }
+
+    fn end_move(&mut self) {
+        self.history.push(std::mem::take(&mut self.pending));
+        self.undone.clear();
+        self.changes += 1;
+    }
+
+    fn set_cell(&mut self, x: usize, y: usize, cell: Cell) {
+        self.pending.cells.push((x, y, self.cells[y][x]));
+        self.cells[y][x] = cell;
+    }
+
+    /// Call before changing any mines during a move.
+    fn save_mines_for_undo(&mut self) {
+        if self.pending.mines.is_none() {
+            self.pending.mines = Some(self.mines.clone());
+        }
+    }
+
+    fn swap_change(&mut self, change: &mut Change) {
+        for (x, y, cell) in change.cells.iter_mut().rev() {
+            std::mem::swap(&mut self.cells[*y][*x], cell);
+        }
+        if let Some(mines) = change.mines.as_mut() {
+            std::mem::swap(&mut self.mines, mines);
+            self.generate_mine_counts();
+        }
+        std::mem::swap(&mut self.mines_placed, &mut change.mines_placed);
+        if !self.mines_placed {
+            // So the next first click places them just like a new game would.
+            if let Some(seed) = self.seed {
+                self.rng = fastrand::Rng::with_seed(seed);
+            }
+        }
+        std::mem::swap(&mut self.move_count, &mut change.move_count);
+        std::mem::swap(&mut self.state, &mut change.state);
+    }
+
+    fn update_state(&mut self, opened_a_mine: bool) {
+        self.state = if opened_a_mine {
+            GameState::Lost
+        } else if self.all_safe_cells_opened() {
+            GameState::Won
+        } else {
+            GameState::InProgress
+        };
+    }
+
+    fn initialize_mines(&mut self, first_click: (usize, usize)) {
+        let (click_x, click_y) = first_click;
+        let mut safe_zone = Vec::with_capacity(SAFE_CELLS_FOR_FIRST_CLICK);
+        safe_zone.push(self.idx(click_x, click_y));
+        do_surrounding(self.cols, self.rows, click_x, click_y, |sx, sy| {
+            safe_zone.push(sy * self.cols + sx)
+        });
+        let random_indices = self.rng.choose_multiple(
+            0..self.rows * self.cols,
+            self.mine_count + SAFE_CELLS_FOR_FIRST_CLICK,
+        );
+        let mut mines_placed = 0;
+        self.mines.fill(false);
+        if self.mine_count > 0 {
+            for i in random_indices {
+                if safe_zone.contains(&i) {
+                    continue;
+                }
+                self.mines[i] = true;
+                mines_placed += 1;
+                if mines_placed == self.mine_count {
+                    break;
+                }
+            }
+        }
+        debug_assert_eq!(self.mine_count, self.mines.iter().filter(|&&b| b).count());
+    }
+
+    /// Keeps generating layouts until the solver can clear one from the first
+    /// click without guessing. Gives up (keeping the last layout, and noting
+    /// it in `no_guess_failed`) if the board is too dense for that to be
+    /// likely.
+    fn initialize_no_guess_mines(&mut self, first_click: (usize, usize)) {
+        self.no_guess_failed = false;
+        for attempt in 1..=NO_GUESS_ATTEMPTS {
+            self.initialize_mines(first_click);
+            self.generate_mine_counts();
+            if self.is_solvable_from(first_click) {
+                if shared::DEBUG_PRINTS {
+                    println!("No-guess: found a layout after {attempt} attempt(s).");
+                }
+                return;
+            }
+        }
+        if shared::DEBUG_PRINTS {
+            println!("No-guess: gave up after {NO_GUESS_ATTEMPTS} attempts.");
+        }
+        self.no_guess_failed = true;
+    }
+
+    /// Whether the solver can win with this layout, opening `first_click` first.
+    fn is_solvable_from(&self, (x, y): (usize, usize)) -> bool {
+        let mut sim = self.clone();
+        sim.mines_placed = true;
+        sim.anti_guess = false;
+        sim.open(x, y);
+        while sim.state() == GameState::InProgress {
+            let deductions = solver::solve(&sim);
+            if !deductions.iter().any(|d| !d.is_mine) {
+                return false;
+            }
+            for d in deductions {
+                if d.is_mine {
+                    sim.toggle_flag(d.x, d.y);
+                } else {
+                    sim.open(d.x, d.y);
+                }
+            }
+        }
+        sim.state() == GameState::Won
+    }
+
+    fn generate_mine_counts(&mut self) {
+        for cell_y in 0..self.rows {
+            for cell_x in 0..self.cols {
+                let i = self.idx(cell_x, cell_y);
+                self.mine_counts[i] =
+                    count_nearby_mines(self.cols, self.rows, cell_x, cell_y, &self.mines);
+            }
+        }
+    }
+
+    /// Opens the cell. If it's a 0, auto-opens the surrounding cells, etc. If it's
+    /// a mine, tries to move it to a neighboring cell, if it wouldn't change the
+    /// revealed information, to help reduce the need for the player to guess.
+    fn open_cell(&mut self, sx: usize, sy: usize) {
+        let mut cells_to_process = Vec::new();
+        if self.cells[sy][sx].can_open() {
+            cells_to_process.push((sx, sy));
+        }
+        while let Some((x, y)) = cells_to_process.pop() {
+            if self.anti_guess && self.mines[self.idx(x, y)] {
+                _ = self.try_move_mine(x, y);
+            }
+            self.set_cell(x, y, Cell::Opened);
+            if self.mine_counts[self.idx(x, y)] == 0 {
+                do_surrounding(self.cols, self.rows, x, y, |ssx, ssy| {
+                    if self.cells[ssy][ssx].can_open() {
+                        cells_to_process.push((ssx, ssy));
+                    }
+                });
+            }
+        }
+    }
+
+    fn try_move_mine(&mut self, cell_x: usize, cell_y: usize) -> bool {
+        let (cols, rows) = (self.cols, self.rows);
+        let idx = |x: usize, y: usize| y * cols + x;
+        let mut found_solution = false;
+        let mut new_mines = vec![false; self.mines.len()].into_boxed_slice();
+        do_surrounding(cols, rows, cell_x, cell_y, |sx, sy| {
+            // Can't move a mine onto a mine.
+            if found_solution || self.mines[idx(sx, sy)] {
+                if !found_solution && shared::DEBUG_ANTI_GUESS {
+                    println!("Anti-guess: rejected {sx},{sy} because there's a mine there.");
+                }
+                return;
+            }
+
+            new_mines.clone_from_slice(&self.mines);
+            new_mines[idx(cell_x, cell_y)] = false;
+            new_mines[idx(sx, sy)] = true;
+
+            let mut any_changes_to_revealed_numbers = false;
+            let mut numbers_that_would_be_changed = Vec::new();
+            for (x, y) in [(cell_x, cell_y), (sx, sy)] {
+                do_surrounding(cols, rows, x, y, |ssx, ssy| {
+                    if self.cells[ssy][ssx] == Cell::Opened
+                        && self.mine_counts[idx(ssx, ssy)]
+                            != count_nearby_mines(cols, rows, ssx, ssy, &new_mines)
+                    {
+                        any_changes_to_revealed_numbers = true;
+                        if shared::DEBUG_ANTI_GUESS {
+                            numbers_that_would_be_changed.push((
+                                ssx,
+                                ssy,
+                                self.mine_counts[idx(ssx, ssy)],
+                            ));
+                        }
+                    }
+                });
+            }
+            if any_changes_to_revealed_numbers {
+                if shared::DEBUG_ANTI_GUESS {
+                    println!(
+                        "Anti-guess: rejected {sx},{sy} because it would change the {}.",
+                        numbers_that_would_be_changed
+                            .into_iter()
+                            .map(|(_ssx, _ssy, num)| format!("{num}"))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    );
+                }
+                return;
+            }
+
+            found_solution = true;
+        });
+
+        if !found_solution {
+            return false;
+        }
+
+        if shared::DEBUG_ANTI_GUESS {
+            let differences: Vec<_> = (0..rows)
+                .flat_map(|y| (0..cols).map(move |x| (x, y)))
+                .filter(|&(x, y)| {
+                    self.mines[idx(x, y)] != new_mines[idx(x, y)] && (x, y) != (cell_x, cell_y)
+                })
+                .collect();
+            let (new_x, new_y) = match *differences {
+                [coords] => coords,
+                [] => panic!("Moved a mine, but the board is identical?"),
+                [_, _, ..] => panic!("Moved a mine, but multiple cells changed?"),
+            };
+            println!("Anti-guess: moved a mine from {cell_x},{cell_y} to {new_x},{new_y}.");
+        }
+        self.save_mines_for_undo();
+        self.mines = new_mines;
+        // Regenerate the whole board so we can notice bugs more easily.
+        self.generate_mine_counts();
+        true
+    }
+
+    fn all_safe_cells_opened(&self) -> bool {
+        !self
+            .cells
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, _)| (x, y)))
+            .filter(|&(x, y)| !self.mines[self.idx(x, y)])
+            .any(|(x, y)| {
+                if self.cells[y][x].can_open() {
+                    if shared::DEBUG_PRINTS {
+                        println!("Some cells are still unopened (e.g., {x},{y}).");
+                    }
+                    true
+                } else {
+                    false
+                }
+            })
+    }
+}
+
+/// Makes the board for a new game: `cfg.board` or the one from `cfg.code`, if
+/// there is one.
+pub fn new_game(cfg: &Config) -> Board {
+    let mut board = match &cfg.board {
+        Some(board) => board.clone(),
+        None => match cfg.code.as_deref().map(board_code::decode) {
+            Some(Ok(board)) => board,
+            Some(Err(err)) => {
+                eprintln!("Couldn't use the board code: {err}");
+                Board::new(cfg)
+            }
+            None => Board::new(cfg),
+        },
+    };
+    board.question_marks = cfg.question_marks;
+    board
+}
+
+fn count_nearby_mines(
+    cols: usize,
+    rows: usize,
+    cell_x: usize,
+    cell_y: usize,
+    mines: &[bool],
+) -> u8 {
+    let mut count = 0;
+    do_surrounding(cols, rows, cell_x, cell_y, |sx, sy| {
+        if mines[sy * cols + sx] {
+            count += 1;
+        }
+    });
+    count
+}
+
+#[inline]
+pub fn do_surrounding(
+    cols: usize,
+    rows: usize,
+    cell_x: usize,
+    cell_y: usize,
+    mut f: impl FnMut(usize, usize),
+) {
+    if cell_x > 0 {
+        if cell_y > 0 {
+            f(cell_x - 1, cell_y - 1);
+        }
+        f(cell_x - 1, cell_y);
+        if cell_y < rows - 1 {
+            f(cell_x - 1, cell_y + 1);
+        }
+    }
+    {
+        if cell_y > 0 {
+            f(cell_x, cell_y - 1);
+        }
+        // Obviously no need to do f(cell_x, cell_y)
+        if cell_y < rows - 1 {
+            f(cell_x, cell_y + 1);
+        }
+    }
+    if cell_x < cols - 1 {
+        if cell_y > 0 {
+            f(cell_x + 1, cell_y - 1);
+        }
+        f(cell_x + 1, cell_y);
+        if cell_y < rows - 1 {
+            f(cell_x + 1, cell_y + 1);
+        }
+    }
+}
+
+#[cfg(test)]
+fn test_board(cols: usize, rows: usize, mine_count: usize) -> Board {
+    let cfg = Config {
+        cell_cols: cols,
+        cell_rows: rows,
+        mine_count,
+        seed: Some(7),
+        ..Config::default()
+    };
+    Board::new(&cfg)
+}
+
+#[test]
+fn test_first_click_is_safe() {
+    for seed in 0..50 {
+        let cfg = Config {
+            cell_cols: 9,
+            cell_rows: 9,
+            mine_count: 10,
+            seed: Some(seed),
+            ..Config::default()
+        };
+        let mut board = Board::new(&cfg);
+        assert!(board.open(4, 4));
+        assert_ne!(board.state(), GameState::Lost);
+        assert_eq!(board.nearby_mines(4, 4), 0);
+        assert_eq!(board.mines.iter().filter(|&&b| b).count(), 10);
+    }
+}
+
+#[test]
+fn test_same_seed_same_board() {
+    for no_guess in [false, true] {
+        let cfg = Config {
+            cell_cols: 16,
+            cell_rows: 16,
+            mine_count: 40,
+            no_guess,
+            seed: Some(1234),
+            ..Config::default()
+        };
+        let mut a = Board::new(&cfg);
+        let mut b = Board::new(&cfg);
+        a.open(3, 5);
+        b.open(3, 5);
+        assert_eq!(a.seed(), Some(1234));
+        assert_eq!(a.mines, b.mines);
+    }
+}
+
+#[test]
+fn test_undoing_the_first_click_keeps_the_seed() {
+    let cfg = Config {
+        cell_cols: 16,
+        cell_rows: 16,
+        mine_count: 40,
+        seed: Some(99),
+        ..Config::default()
+    };
+    let mut fresh = Board::new(&cfg);
+    fresh.open(8, 3);
+    let mut board = Board::new(&cfg);
+    board.open(3, 5);
+    assert!(board.undo());
+    assert!(!board.mines_placed());
+    board.open(8, 3);
+    assert_eq!(board.mines, fresh.mines);
+}
+
+#[test]
+fn test_no_guess_layout_is_solvable() {
+    for seed in 0..5 {
+        let cfg = Config {
+            cell_cols: 16,
+            cell_rows: 16,
+            mine_count: 40,
+            no_guess: true,
+            seed: Some(seed),
+            ..Config::default()
+        };
+        let mut board = Board::new(&cfg);
+        board.open(0, 0);
+        assert!(board.is_solvable_from((0, 0)));
+        assert!(!board.may_need_guess());
+    }
+
+    // Far too dense for any layout to work out.
+    let cfg = Config {
+        cell_cols: 8,
+        cell_rows: 8,
+        mine_count: 50,
+        no_guess: true,
+        seed: Some(1),
+        ..Config::default()
+    };
+    let mut board = Board::new(&cfg);
+    board.open(0, 0);
+    assert!(board.may_need_guess());
+}
+
+#[test]
+fn test_flag_chord_and_win() {
+    // Everything around the mine at 2,2 that isn't next to a 0 stays unopened.
+    let mut mines = vec![false; 16].into_boxed_slice();
+    mines[2 * 4 + 2] = true;
+    let mut board = Board::with_mines(4, 4, mines);
+    assert!(board.mines_placed());
+    assert!(!board.has_started());
+    assert!(board.open(0, 0));
+    assert!(board.has_started());
+    assert_eq!(board.cell(3, 3), Cell::Unopened);
+    assert_eq!(board.state(), GameState::InProgress);
+
+    assert!(!board.chord(1, 2), "not enough flags");
+    assert!(board.toggle_flag(2, 2));
+    assert_eq!(board.mines_left(), 0);
+    assert!(board.chord(1, 2));
+    assert!(board.chord(2, 1));
+    assert_eq!(board.state(), GameState::InProgress);
+    assert!(board.chord(2, 3));
+    assert_eq!(board.state(), GameState::Won);
+    // Nothing happens once the game is over.
+    assert!(!board.toggle_flag(2, 2));
+    assert_eq!(
+        board.clicks(),
+        Clicks {
+            total: 6,
+            effective: 5
+        }
+    );
+}
+
+#[test]
+fn test_question_marks() {
+    let mut mines = vec![false; 16].into_boxed_slice();
+    mines[2 * 4 + 2] = true;
+    let mut board = Board::with_mines(4, 4, mines);
+    board.set_question_marks(true);
+    assert!(board.open(0, 0));
+
+    assert!(board.toggle_flag(3, 3));
+    assert!(board.toggle_flag(3, 3));
+    assert_eq!(board.cell(3, 3), Cell::Questioned);
+    assert!(board.toggle_flag(3, 3));
+    assert_eq!(board.cell(3, 3), Cell::Unopened);
+
+    board.set_question_marks(false);
+    assert!(board.toggle_flag(3, 3));
+    assert!(board.toggle_flag(3, 3));
+    assert_eq!(board.cell(3, 3), Cell::Unopened);
+
+    board.set_question_marks(true);
+    assert!(board.toggle_flag(3, 3));
+    assert!(board.toggle_flag(3, 3));
+    // Question marks aren't flags, for counting or chording.
+    assert_eq!(board.mines_left(), 1);
+    assert!(board.toggle_flag(2, 2));
+    assert!(board.chord(2, 1));
+    assert!(board.chord(3, 2));
+    assert_eq!(board.cell(3, 3), Cell::Opened);
+}
+
+#[test]
+fn test_undo_and_redo() {
+    let mut board = test_board(9, 9, 10);
+    board.open(4, 4);
+    let (mine_x, mine_y) = (0..81)
+        .map(|i| (i % 9, i / 9))
+        .find(|&(x, y)| board.is_mine(x, y) && board.cell(x, y) == Cell::Unopened)
+        .unwrap();
+    let before: Vec<Vec<Cell>> = board.cells().clone();
+
+    board.toggle_flag(mine_x, mine_y);
+    board.toggle_flag(mine_x, mine_y);
+    board.anti_guess = false;
+    board.open(mine_x, mine_y);
+    assert_eq!(board.state(), GameState::Lost);
+
+    // Undo the fatal click and both flag changes
+    let changes = board.changes();
+    assert!(board.undo());
+    assert_eq!(board.changes(), changes + 1);
+    assert_eq!(board.state(), GameState::InProgress);
+    assert!(board.undo());
+    assert_eq!(board.cell(mine_x, mine_y), Cell::Flagged);
+    assert!(board.undo());
+    assert_eq!(board.cells(), &before);
+    assert_eq!(board.undo_count(), 3);
+
+    assert!(board.redo());
+    assert_eq!(board.cell(mine_x, mine_y), Cell::Flagged);
+
+    // Undoing the first move takes the mines away too.
+    assert!(board.undo());
+    assert!(board.undo());
+    assert!(!board.undo());
+    assert!((0..81).all(|i| !board.is_mine(i % 9, i / 9)));
+    assert!(board.redo());
+    assert_eq!(board.cells(), &before);
+    assert!(board.is_mine(mine_x, mine_y));
+
+    // A new move can't be redone over.
+    board.toggle_flag(mine_x, mine_y);
+    assert!(!board.redo());
+}
+
+#[test]
+fn test_open_mine_loses() {
+    let mut mines = vec![false; 16].into_boxed_slice();
+    mines[0] = true;
+    let mut board = Board::with_mines(4, 4, mines);
+    assert!(board.open(0, 0));
+    assert_eq!(board.state(), GameState::Lost);
+}
@@ -0,0 +1,99 @@
+//! Short text codes for sharing an exact mine layout.
+//!
+//! A code is a string of bits written in Crockford's base 32: 16 bits of
+//! columns, 16 bits of rows, then one bit per cell (in row-major order) that's
+//! set if it's a mine, padded with zeros to a whole digit. Nothing about the
+//! random generator goes into it, so codes stay valid if it ever changes.
+
+use crate::board::Board;
+
+const DIGITS: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Makes the code for the board's mines. They need to be placed already.
+pub fn encode(board: &Board) -> String {
+    let mut bits = Vec::with_capacity(32 + board.cols() * board.rows());
+    push_bits(&mut bits, board.cols() as u64, 16);
+    push_bits(&mut bits, board.rows() as u64, 16);
+    for y in 0..board.rows() {
+        for x in 0..board.cols() {
+            bits.push(board.is_mine(x, y));
+        }
+    }
+    bits.chunks(5)
+        .map(|chunk| {
+            let digit = (0..5).fold(0, |acc, i| {
+                acc << 1 | usize::from(chunk.get(i) == Some(&true))
+            });
+            char::from(DIGITS[digit])
+        })
+        .collect()
+}
+
+/// Makes a board with the mines from a code. Case, dashes and spaces don't
+/// matter, and the easily confused letters I, L and O are read as 1 and 0.
+pub fn decode(code: &str) -> Result<Board, &'static str> {
+    let mut bits = Vec::with_capacity(code.len() * 5);
+    for c in code.chars().filter(|&c| c != '-' && !c.is_whitespace()) {
+        let c = match c.to_ascii_uppercase() {
+            'I' | 'L' => '1',
+            'O' => '0',
+            c => c,
+        };
+        let digit = DIGITS
+            .iter()
+            .position(|&d| char::from(d) == c)
+            .ok_or("the code has a character that isn't allowed")?;
+        push_bits(&mut bits, digit as u64, 5);
+    }
+
+    if bits.len() < 32 {
+        return Err("the code is too short");
+    }
+    let cols = read_bits(&bits[..16]);
+    let rows = read_bits(&bits[16..32]);
+    let mines = &bits[32..];
+    let cell_count = cols * rows;
+    if cols == 0 || rows == 0 {
+        return Err("the board is empty");
+    }
+    // Only the last digit can have padding, and it has to be zeros.
+    if mines.len() < cell_count || mines.len() - cell_count >= 5 {
+        return Err("the code is the wrong length for its board size");
+    }
+    if mines[cell_count..].contains(&true) {
+        return Err("the code has extra bits at the end");
+    }
+
+    Ok(Board::with_mines(cols, rows, mines[..cell_count].into()))
+}
+
+fn push_bits(bits: &mut Vec<bool>, value: u64, count: u32) {
+    bits.extend((0..count).rev().map(|i| value >> i & 1 == 1));
+}
+
+fn read_bits(bits: &[bool]) -> usize {
+    bits.iter().fold(0, |acc, &bit| acc << 1 | usize::from(bit))
+}
+
+#[test]
+fn test_board_code_round_trip() {
+    let mines: Box<[bool]> = [true, false, false, true, false, true, false, false, false]
+        .into_iter()
+        .cycle()
+        .take(7 * 5)
+        .collect();
+    let board = Board::with_mines(7, 5, mines.clone());
+    let code = encode(&board);
+    // 32 bits of size and 35 of mines, in 5 bit digits
+    assert_eq!(code.len(), 14);
+
+    let decoded = decode(&code.to_lowercase()).unwrap();
+    assert_eq!((decoded.cols(), decoded.rows()), (7, 5));
+    assert!((0..35).all(|i| decoded.is_mine(i % 7, i / 7) == mines[i]));
+    assert_eq!(encode(&decoded), code);
+
+    assert!(decode("").is_err());
+    assert!(decode(&code[..13]).is_err());
+    assert!(decode(&format!("{code}0")).is_err());
+    assert!(decode(&code.replace('0', "U")).is_err());
+}
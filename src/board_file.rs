@@ -0,0 +1,163 @@
+//! A plain text format for whole boards, for writing puzzles and bug repros by
+//! hand.
+//!
+//! Each line is a row of cells:
+//!
+//! - `.` unopened, `*` unopened mine
+//! - `0`-`8` opened, which has to match the mines around it
+//! - `F` flagged mine, `f` flagged safe cell
+//! - `Q` question-marked mine, `q` question-marked safe cell
+//! - `X` opened mine, for boards that were lost
+//!
+//! Blank lines and lines starting with `#` are skipped. The mine count comes
+//! from the mines.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::board::{Board, Cell, Clicks};
+use crate::shared::{self, Config};
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// The character for a cell, as in the file.
+pub fn cell_char(board: &Board, x: usize, y: usize) -> char {
+    match (board.cell(x, y), board.is_mine(x, y)) {
+        (Cell::Unopened, false) => '.',
+        (Cell::Unopened, true) => '*',
+        (Cell::Opened, false) => char::from(b'0' + board.nearby_mines(x, y)),
+        (Cell::Opened, true) => 'X',
+        (Cell::Flagged, true) => 'F',
+        (Cell::Flagged, false) => 'f',
+        (Cell::Questioned, true) => 'Q',
+        (Cell::Questioned, false) => 'q',
+    }
+}
+
+/// Reads a row of cells. Returns the cells and which of them are mines.
+/// Opened cells' numbers aren't checked.
+pub fn parse_row(line: &str) -> io::Result<(Vec<Cell>, Vec<bool>)> {
+    line.chars()
+        .map(|c| match c {
+            '.' => Ok((Cell::Unopened, false)),
+            '*' => Ok((Cell::Unopened, true)),
+            '0'..='8' => Ok((Cell::Opened, false)),
+            'X' => Ok((Cell::Opened, true)),
+            'F' => Ok((Cell::Flagged, true)),
+            'f' => Ok((Cell::Flagged, false)),
+            'Q' => Ok((Cell::Questioned, true)),
+            'q' => Ok((Cell::Questioned, false)),
+            _ => Err(invalid(format!("bad cell '{c}'"))),
+        })
+        .collect::<io::Result<Vec<_>>>()
+        .map(|cells| cells.into_iter().unzip())
+}
+
+pub fn serialize(board: &Board) -> String {
+    let mut s = String::new();
+    for y in 0..board.rows() {
+        for x in 0..board.cols() {
+            s.push(cell_char(board, x, y));
+        }
+        s.push('\n');
+    }
+    s
+}
+
+pub fn parse(s: &str) -> io::Result<Board> {
+    let mut mines = Vec::new();
+    let mut cells = Vec::new();
+    for line in s.lines().map(str::trim_end) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (row, row_mines) = parse_row(line)?;
+        if cells
+            .first()
+            .is_some_and(|first: &Vec<Cell>| first.len() != row.len())
+        {
+            return Err(invalid(format!("row '{line}' is a different length")));
+        }
+        cells.push(row);
+        mines.extend(row_mines);
+    }
+    let rows = cells.len();
+    let cols = cells.first().map_or(0, Vec::len);
+    if cols == 0 {
+        return Err(invalid("there isn't a board"));
+    }
+
+    let cfg = Config {
+        cell_cols: cols,
+        cell_rows: rows,
+        mine_count: mines.iter().filter(|&&m| m).count(),
+        ..Config::default()
+    };
+    let any_opened = cells.iter().flatten().any(|&c| c == Cell::Opened);
+    // Opening cells by hand isn't a move, but it does mean the game has started.
+    let board = Board::restore(
+        &cfg,
+        Some(mines.into_boxed_slice()),
+        cells,
+        // The clock waits for the player's first click, as always.
+        false,
+        usize::from(any_opened),
+        0,
+        Clicks::default(),
+    );
+
+    for (y, line) in s
+        .lines()
+        .filter(|line| !line.trim_end().is_empty() && !line.starts_with('#'))
+        .enumerate()
+    {
+        for (x, c) in line.chars().enumerate() {
+            if c.is_ascii_digit() && c != cell_char(&board, x, y) {
+                return Err(invalid(format!(
+                    "the number at {x},{y} should be {}",
+                    board.nearby_mines(x, y)
+                )));
+            }
+        }
+    }
+    Ok(board)
+}
+
+pub fn read(path: &Path) -> io::Result<Board> {
+    parse(&fs::read_to_string(path)?)
+}
+
+/// Writes a new board file in the data directory. Returns where it went.
+pub fn write(board: &Board) -> io::Result<PathBuf> {
+    shared::write_new_data_file("board", "txt", &serialize(board))
+}
+
+#[test]
+fn test_board_file() {
+    let s = "# A puzzle\n\
+             \n\
+             23*.\n\
+             *F2.\n\
+             f..q\n";
+    let board = parse(s).unwrap();
+    assert_eq!((board.cols(), board.rows()), (4, 3));
+    assert_eq!(board.mine_count(), 3);
+    assert_eq!(board.cell(0, 0), Cell::Opened);
+    assert_eq!(board.cell(0, 2), Cell::Flagged);
+    assert_eq!(board.cell(3, 2), Cell::Questioned);
+    assert!(board.is_mine(1, 1) && !board.is_mine(0, 2));
+    assert_eq!(
+        serialize(&board),
+        s.lines().skip(2).collect::<Vec<_>>().join("\n") + "\n"
+    );
+
+    assert!(parse("").is_err());
+    assert!(parse("..\n...\n").is_err());
+    assert!(parse(".?\n").is_err());
+    // The 1 should be a 2.
+    assert!(parse("1*\n*.\n").is_err());
+}
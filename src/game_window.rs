@@ -1,30 +1,100 @@
-// It complains about `&Vec<Vec<_>>`. I'm not incurring the complexity of making
-// them _and_ their contents generic.
-#![allow(clippy::ptr_arg)]
-
 use ab_glyph::{Font, FontRef, ScaleFont};
 use glam::IVec2;
-use minifb::{Key, Menu, MenuHandle, MouseButton, MouseMode, Window};
+use minifb::{Menu, MenuHandle, MouseButton, MouseMode, Window, WindowOptions};
 use std::time::{Duration, Instant};
 
-use crate::{shared, text};
-use shared::{Config, Lang, CELL_SIZE, CELL_SIZE_F};
+use crate::{
+    bindings, board, board_code, board_file, metrics, probability, records, replay, save, shared,
+    solver, stats, text, theme,
+};
+use bindings::{Action as BindingAction, Bindings, Trigger};
+use board::{Board, Cell, GameState};
+use metrics::Metrics;
+use records::{BoardKey, Record, Records};
+use replay::{Action, Input, Playback, Replay, Speed};
+use save::SavedGame;
+use shared::{
+    Config, Lang, Stopwatch, DEFAULT_CELL_SIZE, MAX_CELL_SIZE, MIN_CELL_SIZE, STATUS_BAR_HEIGHT,
+};
+use stats::Stats;
+use theme::Theme;
 
-/// The number of cells that *must* be free of mines at the start of the game.
-pub const SAFE_CELLS_FOR_FIRST_CLICK: usize = 9; // 1 + 8 surrounding cells
+/// The biggest window a new game opens with. Bigger boards get smaller cells.
+pub const MAX_START_WIDTH: usize = 1280;
+pub const MAX_START_HEIGHT: usize = 720;
+/// Small enough for the smallest board, big enough for the status bar.
+pub const MIN_WINDOW_WIDTH: usize = 4 * (DEFAULT_CELL_SIZE + 1) + 1;
+pub const MIN_WINDOW_HEIGHT: usize = STATUS_BAR_HEIGHT + DEFAULT_CELL_SIZE + 2;
+/// How many pixels each notch of Ctrl+wheel grows or shrinks cells by.
+const ZOOM_STEP: usize = 4;
+/// How many cells each notch of the wheel scrolls by.
+const WHEEL_SCROLL_CELLS: f32 = 3.0;
+/// How far the mouse has to move with the right button held before it pans
+/// instead of flagging.
+const PAN_START_DISTANCE: f32 = 8.0;
+/// The longest side of the minimap, in pixels.
+const MINIMAP_SIZE: usize = 160;
+const MINIMAP_MARGIN: usize = 8;
 
-static DIGITS_EN: [char; 10] = ['0', '1', '2', '3', '4', '5', '6', '7', '8', '9'];
+pub static DIGITS_EN: [char; 10] = ['0', '1', '2', '3', '4', '5', '6', '7', '8', '9'];
 // Unlike English, these aren't in order in Unicode, so we can't just add a constant to convert.
-static DIGITS_JP: [char; 10] = ['0', '一', '二', '三', '四', '五', '六', '七', '八', '九'];
+pub static DIGITS_JP: [char; 10] = ['0', '一', '二', '三', '四', '五', '六', '七', '八', '九'];
 
 pub enum GameEnd {
     Restart,
+    Load(Box<SavedGame>),
     Quit,
 }
 
-pub fn run(cfg: &mut Config) -> GameEnd {
-    cfg.buffer_width = (CELL_SIZE + 1) * cfg.cell_cols + 1;
-    cfg.buffer_height = (CELL_SIZE + 1) * cfg.cell_rows + 1;
+/// Plays a game, either a new one or `saved_game`, or watches `playback`.
+pub fn run(
+    cfg: &mut Config,
+    saved_game: Option<SavedGame>,
+    mut playback: Option<Playback>,
+) -> GameEnd {
+    // A save is only kept until it's back in play, so it can't be offered
+    // again once the game has moved on. Quitting saves it again.
+    if saved_game.is_some() {
+        save::delete();
+    }
+    // Whether the save file holds this game, so it can be deleted once the
+    // game is over or replaced.
+    let mut is_saved = false;
+    let (mut board, mut stopwatch, mut hints_used, mut inputs) = match saved_game {
+        Some(mut game) => {
+            cfg.no_guess = game.no_guess;
+            game.board.set_question_marks(cfg.question_marks);
+            (
+                game.board,
+                Stopwatch::with_elapsed(game.elapsed),
+                game.hints_used,
+                game.inputs,
+            )
+        }
+        None => (
+            board::new_game(cfg),
+            Stopwatch::default(),
+            0,
+            Some(Vec::new()),
+        ),
+    };
+    if let Some(playback) = &mut playback {
+        board = playback.restart();
+    }
+    // Saved games and board codes bring their own sizes.
+    cfg.cell_cols = board.cols();
+    cfg.cell_rows = board.rows();
+    cfg.mine_count = board.mine_count();
+    // Big boards start with smaller cells, so the window fits on screen.
+    cfg.fit_cell_size(MAX_START_WIDTH, MAX_START_HEIGHT);
+    cfg.cell_size = cfg.cell_size.min(DEFAULT_CELL_SIZE);
+    // Boards too big even then get scrolled around.
+    cfg.fit_buffer_to_board();
+    cfg.buffer_width = cfg.buffer_width.min(MAX_START_WIDTH);
+    cfg.buffer_height = cfg.buffer_height.min(MAX_START_HEIGHT);
+    cfg.scroll = (0, 0);
+
+    let bindings = Bindings::load();
 
     let font_en = FontRef::try_from_slice(shared::FIRA_CODE_BYTES).unwrap();
     let font_jp = FontRef::try_from_slice(shared::NOTO_SANS_JP_BYTES).unwrap();
@@ -37,7 +107,10 @@ pub fn run(cfg: &mut Config) -> GameEnd {
         "Minesweeper",
         cfg.buffer_width,
         cfg.buffer_height,
-        Default::default(),
+        WindowOptions {
+            resize: true,
+            ..WindowOptions::default()
+        },
     )
     .unwrap();
 
@@ -45,22 +118,166 @@ pub fn run(cfg: &mut Config) -> GameEnd {
     const MENU_ID_QUIT: usize = 2;
     const MENU_ID_LANG_EN: usize = 3;
     const MENU_ID_LANG_JP: usize = 4;
+    const MENU_ID_SHOW_ODDS: usize = 5;
+    const MENU_ID_HINT: usize = 6;
+    const MENU_ID_UNDO: usize = 7;
+    const MENU_ID_REDO: usize = 8;
+    const MENU_ID_SAVE: usize = 9;
+    const MENU_ID_LOAD: usize = 10;
+    const MENU_ID_BOARD_CODE: usize = 11;
+    const MENU_ID_RECORDS: usize = 12;
+    const MENU_ID_STATS: usize = 13;
+    const MENU_ID_RESET_STATS: usize = 14;
+    const MENU_ID_SAVE_REPLAY: usize = 15;
+    const MENU_ID_SPEED_NORMAL: usize = 16;
+    const MENU_ID_SPEED_DOUBLE: usize = 17;
+    const MENU_ID_SPEED_INSTANT: usize = 18;
+    const MENU_ID_EXPORT_RAWVF: usize = 19;
+    const MENU_ID_EXPORT_BOARD: usize = 20;
+    const MENU_ID_KEYBOARD_HELP: usize = 21;
+    const MENU_ID_QUESTION_MARKS: usize = 22;
+    const MENU_ID_THEME_LIGHT: usize = 23;
+    const MENU_ID_THEME_DARK: usize = 24;
+    const MENU_ID_THEME_CLASSIC: usize = 25;
+    const MENU_ID_THEME_FILE: usize = 26;
+    const MENU_ID_THEME_HIGH_CONTRAST: usize = 27;
+    const MENU_ID_COLOR_BLIND: usize = 28;
 
-    fn create_menubar(cfg: &Config, window: &mut Window) -> Vec<MenuHandle> {
+    /// The menu items with shortcuts, and the actions that bind them.
+    const MENU_ACTIONS: &[(usize, BindingAction)] = &[
+        (MENU_ID_NEW_GAME, BindingAction::NewGame),
+        (MENU_ID_QUIT, BindingAction::Quit),
+        (MENU_ID_SAVE, BindingAction::Save),
+        (MENU_ID_LOAD, BindingAction::Load),
+        (MENU_ID_BOARD_CODE, BindingAction::BoardCode),
+        (MENU_ID_RECORDS, BindingAction::Records),
+        (MENU_ID_SHOW_ODDS, BindingAction::ShowOdds),
+        (MENU_ID_HINT, BindingAction::Hint),
+        (MENU_ID_UNDO, BindingAction::Undo),
+        (MENU_ID_REDO, BindingAction::Redo),
+        (MENU_ID_SAVE_REPLAY, BindingAction::SaveReplay),
+        (MENU_ID_EXPORT_RAWVF, BindingAction::ExportRawvf),
+        (MENU_ID_KEYBOARD_HELP, BindingAction::KeyboardHelp),
+        (MENU_ID_SPEED_NORMAL, BindingAction::SpeedNormal),
+        (MENU_ID_SPEED_DOUBLE, BindingAction::SpeedDouble),
+        (MENU_ID_SPEED_INSTANT, BindingAction::SpeedInstant),
+    ];
+
+    fn add_menu_item(menu: &mut Menu, bindings: &Bindings, name: &str, id: usize) {
+        let mut item = menu.add_item(name, id);
+        let shortcut = MENU_ACTIONS
+            .iter()
+            .find(|m| m.0 == id)
+            .and_then(|&(_, action)| bindings.shortcut(action));
+        if let Some((key, modifier)) = shortcut {
+            item = item.shortcut(key, modifier);
+        }
+        item.build();
+    }
+
+    fn create_menubar(cfg: &Config, bindings: &Bindings, window: &mut Window) -> Vec<MenuHandle> {
         let mut menu_handles = Vec::new();
 
         let mut game_menu = Menu::new(cfg.en_jp("Game", "ゲーム")).unwrap();
-        game_menu
-            .add_item(cfg.en_jp("New Game", "新しいゲーム"), MENU_ID_NEW_GAME)
-            .shortcut(Key::N, minifb::MENU_KEY_CTRL)
-            .build();
-        game_menu
-            .add_item(cfg.en_jp("Quit", "ゲームをやめる"), MENU_ID_QUIT)
-            .shortcut(Key::F4, minifb::MENU_KEY_ALT)
-            .build();
+        add_menu_item(
+            &mut game_menu,
+            bindings,
+            cfg.en_jp("New Game", "新しいゲーム"),
+            MENU_ID_NEW_GAME,
+        );
+        add_menu_item(
+            &mut game_menu,
+            bindings,
+            cfg.en_jp("Undo", "元に戻す"),
+            MENU_ID_UNDO,
+        );
+        add_menu_item(
+            &mut game_menu,
+            bindings,
+            cfg.en_jp("Redo", "やり直す"),
+            MENU_ID_REDO,
+        );
+        add_menu_item(
+            &mut game_menu,
+            bindings,
+            cfg.en_jp("Hint", "ヒント"),
+            MENU_ID_HINT,
+        );
+        add_menu_item(
+            &mut game_menu,
+            bindings,
+            cfg.en_jp("Save", "保存"),
+            MENU_ID_SAVE,
+        );
+        add_menu_item(
+            &mut game_menu,
+            bindings,
+            cfg.en_jp("Load", "読み込む"),
+            MENU_ID_LOAD,
+        );
+        add_menu_item(
+            &mut game_menu,
+            bindings,
+            cfg.en_jp("Show Board Code", "盤面コードを表示"),
+            MENU_ID_BOARD_CODE,
+        );
+        add_menu_item(
+            &mut game_menu,
+            bindings,
+            cfg.en_jp("Save Replay", "リプレイを保存"),
+            MENU_ID_SAVE_REPLAY,
+        );
+        add_menu_item(
+            &mut game_menu,
+            bindings,
+            cfg.en_jp("Export Replay (RAWVF)", "リプレイをエクスポート（RAWVF）"),
+            MENU_ID_EXPORT_RAWVF,
+        );
+        add_menu_item(
+            &mut game_menu,
+            bindings,
+            cfg.en_jp("Export Board", "盤面をエクスポート"),
+            MENU_ID_EXPORT_BOARD,
+        );
+        add_menu_item(
+            &mut game_menu,
+            bindings,
+            cfg.en_jp("Records", "記録"),
+            MENU_ID_RECORDS,
+        );
+        add_menu_item(
+            &mut game_menu,
+            bindings,
+            cfg.en_jp("Statistics", "統計"),
+            MENU_ID_STATS,
+        );
+        add_menu_item(
+            &mut game_menu,
+            bindings,
+            cfg.en_jp("Reset Statistics", "統計をリセット"),
+            MENU_ID_RESET_STATS,
+        );
+        add_menu_item(
+            &mut game_menu,
+            bindings,
+            cfg.en_jp("Quit", "ゲームをやめる"),
+            MENU_ID_QUIT,
+        );
         menu_handles.push(window.add_menu(&game_menu));
 
         let mut options_menu = Menu::new(cfg.en_jp("Options", "設定")).unwrap();
+        add_menu_item(
+            &mut options_menu,
+            bindings,
+            cfg.en_jp("Show Mine Odds", "地雷の確率を表示"),
+            MENU_ID_SHOW_ODDS,
+        );
+        add_menu_item(
+            &mut options_menu,
+            bindings,
+            cfg.en_jp("Question Marks", "？マーク"),
+            MENU_ID_QUESTION_MARKS,
+        );
         let mut lang_menu = Menu::new(cfg.en_jp("Language", "言語")).unwrap();
         lang_menu
             .add_item(cfg.en_jp("English", "English（英語）"), MENU_ID_LANG_EN)
@@ -69,8 +286,53 @@ pub fn run(cfg: &mut Config) -> GameEnd {
             .add_item(cfg.en_jp("日本語 (Japanese)", "日本語"), MENU_ID_LANG_JP)
             .build();
         options_menu.add_sub_menu(cfg.en_jp("Language", "言語"), &lang_menu);
+        let mut speed_menu = Menu::new(cfg.en_jp("Replay Speed", "リプレイ速度")).unwrap();
+        add_menu_item(&mut speed_menu, bindings, "1x", MENU_ID_SPEED_NORMAL);
+        add_menu_item(&mut speed_menu, bindings, "2x", MENU_ID_SPEED_DOUBLE);
+        add_menu_item(
+            &mut speed_menu,
+            bindings,
+            cfg.en_jp("Instant", "一瞬"),
+            MENU_ID_SPEED_INSTANT,
+        );
+        options_menu.add_sub_menu(cfg.en_jp("Replay Speed", "リプレイ速度"), &speed_menu);
+        let mut theme_menu = Menu::new(cfg.en_jp("Theme", "テーマ")).unwrap();
+        for (name, id) in [
+            (cfg.en_jp("Light", "ライト"), MENU_ID_THEME_LIGHT),
+            (cfg.en_jp("Dark", "ダーク"), MENU_ID_THEME_DARK),
+            (
+                cfg.en_jp("Classic Grey", "クラシック"),
+                MENU_ID_THEME_CLASSIC,
+            ),
+            (
+                cfg.en_jp("High Contrast", "ハイコントラスト"),
+                MENU_ID_THEME_HIGH_CONTRAST,
+            ),
+            (
+                cfg.en_jp("From Theme File", "テーマファイルから"),
+                MENU_ID_THEME_FILE,
+            ),
+        ] {
+            theme_menu.add_item(name, id).build();
+        }
+        options_menu.add_sub_menu(cfg.en_jp("Theme", "テーマ"), &theme_menu);
+        add_menu_item(
+            &mut options_menu,
+            bindings,
+            cfg.en_jp("Colour-Blind Numbers", "色覚に配慮した数字"),
+            MENU_ID_COLOR_BLIND,
+        );
         menu_handles.push(window.add_menu(&options_menu));
 
+        let mut help_menu = Menu::new(cfg.en_jp("Help", "ヘルプ")).unwrap();
+        add_menu_item(
+            &mut help_menu,
+            bindings,
+            cfg.en_jp("Keyboard Controls", "キーボード操作"),
+            MENU_ID_KEYBOARD_HELP,
+        );
+        menu_handles.push(window.add_menu(&help_menu));
+
         menu_handles
     }
     fn destroy_menubar(window: &mut Window, menu_handles: Vec<MenuHandle>) {
@@ -79,41 +341,310 @@ pub fn run(cfg: &mut Config) -> GameEnd {
         }
     }
 
-    let mut menu_handles = create_menubar(cfg, &mut window);
+    let mut menu_handles = create_menubar(cfg, &bindings, &mut window);
 
     let mut showing_message_since: Option<Instant> = None;
 
-    let mut rng = fastrand::Rng::new();
-
-    // Whether each cell has a mine. Gets initialized on first click so we can ensure the player doesn't immediately lose.
-    let mut mines: Box<[bool]> = vec![false; cfg.cell_rows * cfg.cell_cols].into_boxed_slice();
-    let mut mine_counts: Box<[u8]> = vec![0; cfg.cell_rows * cfg.cell_cols].into_boxed_slice();
-
-    let mut cells: Vec<Vec<Cell>> = vec![vec![Cell::Unopened; cfg.cell_cols]; cfg.cell_rows];
+    let no_guess = cfg.no_guess;
+    let save_game =
+        move |board: &Board, stopwatch: &Stopwatch, hints_used, inputs: &Option<Vec<Input>>| {
+            save::write(&SavedGame {
+                board: board.clone(),
+                no_guess,
+                elapsed: stopwatch.elapsed(),
+                hints_used,
+                inputs: inputs.clone(),
+            })
+        };
 
-    let mut mouse_left = CellsMouseState {
-        button: MouseButton::Left,
-        held: None,
-    };
-    let mut mouse_middle = CellsMouseState {
-        button: MouseButton::Middle,
-        held: None,
-    };
-    let mut mouse_right = CellsMouseState {
-        button: MouseButton::Right,
-        held: None,
-    };
+    let mut mouse = CellsMouse::new();
+    let mut right_drag = RightDrag::default();
 
     let mut needs_update = true;
-    let mut move_count = 0;
-    let mut is_game_over = false;
+    let mut show_odds = false;
+    // The odds for every cell, and the `board.changes()` they're for. They
+    // take a while to work out on big boards.
+    let mut odds: Option<(usize, Vec<Option<f64>>)> = None;
+    let mut hint: Option<(usize, usize)> = None;
+    // The keyboard's cell cursor. Hidden until a key uses it.
+    let mut cursor: Option<(usize, usize)> = None;
+    let mut notice: Option<String> = None;
+    let mut is_game_over = board.is_game_over();
     let mut just_won = false;
     let mut just_lost = false;
-    while window.is_open() && !window.is_key_down(Key::Escape) {
-        if let Some(menu_id) = window.is_menu_pressed() {
+    // Where the win placed in the records, if it did.
+    let mut new_record: Option<usize> = None;
+    // Only a game's first ending counts for the stats, even if it's undone.
+    let mut stats_recorded = false;
+    let mut left_was_down = false;
+    // Whether the left click started on the restart button.
+    let mut restart_pressed = false;
+    // What the status bar was last drawn with.
+    let mut status_drawn = None;
+    let mut last_frame = Instant::now();
+    while window.is_open() && !bindings.is_down(&window, BindingAction::CloseWindow) {
+        let menu_id = window
+            .is_menu_pressed()
+            .or_else(|| pressed_menu_shortcut(&window, &bindings, MENU_ACTIONS));
+        if let Some(menu_id) = menu_id {
             match menu_id {
-                MENU_ID_NEW_GAME => return GameEnd::Restart,
-                MENU_ID_QUIT => return GameEnd::Quit,
+                MENU_ID_NEW_GAME => {
+                    if is_saved {
+                        save::delete();
+                    }
+                    return GameEnd::Restart;
+                }
+                MENU_ID_QUIT => break,
+                // Replays are only for watching.
+                MENU_ID_SAVE | MENU_ID_SAVE_REPLAY | MENU_ID_EXPORT_RAWVF | MENU_ID_UNDO
+                | MENU_ID_REDO | MENU_ID_HINT
+                    if playback.is_some() => {}
+                MENU_ID_SAVE => {
+                    notice = Some(match save_game(&board, &stopwatch, hints_used, &inputs) {
+                        Ok(()) => {
+                            is_saved = true;
+                            cfg.en_jp("Game saved.", "保存しました。").to_owned()
+                        }
+                        Err(err) => match cfg.lang {
+                            Lang::En => format!("Couldn't save:\n{err}"),
+                            Lang::Jp => format!("保存できませんでした：\n{err}"),
+                        },
+                    });
+                    needs_update = true;
+                }
+                MENU_ID_BOARD_CODE => {
+                    notice = Some(if board.mines_placed() {
+                        let code = board_code::encode(&board);
+                        // minifb can't copy to the clipboard, but terminals can.
+                        println!("Board code: {code}");
+                        match cfg.lang {
+                            Lang::En => {
+                                format!("Board code:\n{code}\n(Also printed to the console.)")
+                            }
+                            Lang::Jp => {
+                                format!("盤面コード：\n{code}\n（コンソールにも出力しました）")
+                            }
+                        }
+                    } else {
+                        cfg.en_jp(
+                            "There aren't any mines until the first click.",
+                            "最初のクリックまで地雷はありません。",
+                        )
+                        .to_owned()
+                    });
+                    needs_update = true;
+                }
+                MENU_ID_SAVE_REPLAY | MENU_ID_EXPORT_RAWVF => {
+                    let write = if menu_id == MENU_ID_SAVE_REPLAY {
+                        replay::write
+                    } else {
+                        replay::export_rawvf
+                    };
+                    notice = Some(if !board.mines_placed() {
+                        cfg.en_jp(
+                            "There's nothing to replay until the first click.",
+                            "最初のクリックまでリプレイはありません。",
+                        )
+                        .to_owned()
+                    } else if let Some(inputs) = &inputs {
+                        match write(&Replay::new(&board, inputs.clone())) {
+                            Ok(path) => {
+                                println!("Replay saved to {}", path.display());
+                                match cfg.lang {
+                                    Lang::En => format!(
+                                        "Replay saved to:\n{}\n(Also printed to the console.)",
+                                        path.display()
+                                    ),
+                                    Lang::Jp => format!(
+                                        "リプレイの保存先：\n{}\n（コンソールにも出力しました）",
+                                        path.display()
+                                    ),
+                                }
+                            }
+                            Err(err) => match cfg.lang {
+                                Lang::En => format!("Couldn't save the replay:\n{err}"),
+                                Lang::Jp => format!("リプレイを保存できませんでした：\n{err}"),
+                            },
+                        }
+                    } else {
+                        cfg.en_jp(
+                            "This game can't be replayed, since\nnot all of it was recorded.",
+                            "このゲームは全部記録されていないので、\nリプレイできません。",
+                        )
+                        .to_owned()
+                    });
+                    needs_update = true;
+                }
+                MENU_ID_SPEED_NORMAL | MENU_ID_SPEED_DOUBLE | MENU_ID_SPEED_INSTANT => {
+                    if let Some(playback) = &mut playback {
+                        playback.speed = match menu_id {
+                            MENU_ID_SPEED_NORMAL => Speed::Normal,
+                            MENU_ID_SPEED_DOUBLE => Speed::Double,
+                            _ => Speed::Instant,
+                        };
+                    }
+                }
+                MENU_ID_EXPORT_BOARD => {
+                    notice = Some(if board.mines_placed() {
+                        match board_file::write(&board) {
+                            Ok(path) => {
+                                println!("Board saved to {}", path.display());
+                                match cfg.lang {
+                                    Lang::En => format!(
+                                        "Board saved to:\n{}\n(Also printed to the console.)",
+                                        path.display()
+                                    ),
+                                    Lang::Jp => format!(
+                                        "盤面の保存先：\n{}\n（コンソールにも出力しました）",
+                                        path.display()
+                                    ),
+                                }
+                            }
+                            Err(err) => match cfg.lang {
+                                Lang::En => format!("Couldn't save the board:\n{err}"),
+                                Lang::Jp => format!("盤面を保存できませんでした：\n{err}"),
+                            },
+                        }
+                    } else {
+                        cfg.en_jp(
+                            "There aren't any mines until the first click.",
+                            "最初のクリックまで地雷はありません。",
+                        )
+                        .to_owned()
+                    });
+                    needs_update = true;
+                }
+                MENU_ID_KEYBOARD_HELP => {
+                    notice = Some(controls_help(
+                        cfg,
+                        &bindings,
+                        &[
+                            BindingAction::Open,
+                            BindingAction::Flag,
+                            BindingAction::Chord,
+                            BindingAction::CursorUp,
+                            BindingAction::CursorDown,
+                            BindingAction::CursorLeft,
+                            BindingAction::CursorRight,
+                            BindingAction::ScrollUp,
+                            BindingAction::ScrollDown,
+                            BindingAction::ScrollLeft,
+                            BindingAction::ScrollRight,
+                            BindingAction::Undo,
+                            BindingAction::Redo,
+                            BindingAction::Hint,
+                            BindingAction::NewGame,
+                            BindingAction::KeyboardHelp,
+                        ],
+                    ));
+                    needs_update = true;
+                }
+                MENU_ID_RECORDS => {
+                    notice = Some(records::describe(BoardKey::of(cfg), cfg.lang));
+                    needs_update = true;
+                }
+                MENU_ID_STATS => {
+                    notice = Some(stats::describe(BoardKey::of(cfg), cfg.lang));
+                    needs_update = true;
+                }
+                MENU_ID_RESET_STATS => {
+                    notice = Some(match Stats::reset() {
+                        Ok(()) => cfg
+                            .en_jp("Statistics reset.", "統計をリセットしました。")
+                            .to_owned(),
+                        Err(err) => match cfg.lang {
+                            Lang::En => format!("Couldn't reset:\n{err}"),
+                            Lang::Jp => format!("リセットできませんでした：\n{err}"),
+                        },
+                    });
+                    needs_update = true;
+                }
+                MENU_ID_LOAD => match save::read() {
+                    Ok(game) => return GameEnd::Load(Box::new(game)),
+                    Err(err) => {
+                        notice = Some(match cfg.lang {
+                            Lang::En => format!("Couldn't load:\n{err}"),
+                            Lang::Jp => format!("読み込めませんでした：\n{err}"),
+                        });
+                        needs_update = true;
+                    }
+                },
+                MENU_ID_SHOW_ODDS => {
+                    show_odds = !show_odds;
+                    needs_update = true;
+                }
+                MENU_ID_THEME_LIGHT
+                | MENU_ID_THEME_DARK
+                | MENU_ID_THEME_CLASSIC
+                | MENU_ID_THEME_HIGH_CONTRAST
+                | MENU_ID_THEME_FILE => {
+                    cfg.theme = match menu_id {
+                        MENU_ID_THEME_LIGHT => Theme::LIGHT,
+                        MENU_ID_THEME_DARK => Theme::DARK,
+                        MENU_ID_THEME_CLASSIC => Theme::CLASSIC,
+                        MENU_ID_THEME_HIGH_CONTRAST => Theme::HIGH_CONTRAST,
+                        // Read again, so edits show without restarting.
+                        _ => Theme::load(),
+                    };
+                    needs_update = true;
+                }
+                MENU_ID_COLOR_BLIND => {
+                    cfg.color_blind = !cfg.color_blind;
+                    notice = Some(
+                        if cfg.color_blind {
+                            cfg.en_jp(
+                                "Colour-blind numbers on.",
+                                "色覚に配慮した数字をオンにしました。",
+                            )
+                        } else {
+                            cfg.en_jp(
+                                "Colour-blind numbers off.",
+                                "色覚に配慮した数字をオフにしました。",
+                            )
+                        }
+                        .to_owned(),
+                    );
+                    needs_update = true;
+                }
+                MENU_ID_QUESTION_MARKS => {
+                    cfg.question_marks = !cfg.question_marks;
+                    // Replays keep the setting they were recorded with.
+                    if playback.is_none() {
+                        board.set_question_marks(cfg.question_marks);
+                    }
+                    notice = Some(
+                        if cfg.question_marks {
+                            cfg.en_jp("Question marks on.", "？マークをオンにしました。")
+                        } else {
+                            cfg.en_jp("Question marks off.", "？マークをオフにしました。")
+                        }
+                        .to_owned(),
+                    );
+                    needs_update = true;
+                }
+                MENU_ID_UNDO | MENU_ID_REDO => {
+                    let action = if menu_id == MENU_ID_UNDO {
+                        Action::Undo
+                    } else {
+                        Action::Redo
+                    };
+                    let changed = play(&mut board, &mut inputs, &stopwatch, action);
+                    if changed {
+                        // Let the game over check below notice if the game ended (again).
+                        is_game_over = false;
+                        showing_message_since = None;
+                        hint = None;
+                        needs_update = true;
+                    }
+                }
+                MENU_ID_HINT if hint.is_none() => {
+                    hint = probability::hint(&board);
+                    if let Some(cell) = hint {
+                        cfg.scroll_to(cell);
+                        hints_used += 1;
+                        needs_update = true;
+                    }
+                }
                 MENU_ID_LANG_EN | MENU_ID_LANG_JP => {
                     cfg.lang = if menu_id == MENU_ID_LANG_EN {
                         Lang::En
@@ -124,170 +655,341 @@ pub fn run(cfg: &mut Config) -> GameEnd {
                     digits = cfg.en_jp(DIGITS_EN, DIGITS_JP);
                     needs_update = true;
                     destroy_menubar(&mut window, menu_handles);
-                    menu_handles = create_menubar(cfg, &mut window);
+                    menu_handles = create_menubar(cfg, &bindings, &mut window);
                 }
                 _ => {}
             }
         }
 
+        // The board follows the window's size, and Ctrl+wheel zooms it.
+        let (width, height) = window.get_size();
+        let size = (width.max(MIN_WINDOW_WIDTH), height.max(MIN_WINDOW_HEIGHT));
+        if size != (cfg.buffer_width, cfg.buffer_height) {
+            (cfg.buffer_width, cfg.buffer_height) = size;
+            cfg.fit_cell_size(size.0, size.1);
+            cfg.clamp_scroll();
+            buffer.resize(size.0 * size.1, 0);
+            needs_update = true;
+        }
+        match read_wheel(&window) {
+            Some(Wheel::Zoom(grow)) => {
+                cfg.cell_size = zoomed(cfg.cell_size, grow);
+                cfg.clamp_scroll();
+                needs_update = true;
+            }
+            Some(Wheel::Scroll(dx, dy)) => {
+                let old_scroll = cfg.scroll;
+                cfg.scroll_by((dx, dy));
+                needs_update |= cfg.scroll != old_scroll;
+            }
+            None => {}
+        }
+
+        if let Some((scroll, delta)) =
+            right_drag.update(&window, cfg.cell_size, cfg.scroll, cfg.is_scrollable())
+        {
+            mouse.right.cancel();
+            let old_scroll = cfg.scroll;
+            cfg.scroll = scroll;
+            cfg.scroll_by(delta);
+            needs_update |= cfg.scroll != old_scroll;
+        }
+
+        // Clicking or dragging on the minimap jumps there.
+        if window.get_mouse_down(MouseButton::Left) {
+            if let Some(cell) = window
+                .get_mouse_pos(MouseMode::Discard)
+                .and_then(|pos| minimap_cell(cfg, pos))
+            {
+                let (view_cols, view_rows) = cfg.view_size();
+                let old_scroll = cfg.scroll;
+                cfg.scroll = (
+                    cell.0.saturating_sub(view_cols / 2),
+                    cell.1.saturating_sub(view_rows / 2),
+                );
+                cfg.clamp_scroll();
+                needs_update |= cfg.scroll != old_scroll;
+            }
+        }
+
+        if let Some(delta) = scroll_keys(&window, &bindings, cfg.view_size()) {
+            cfg.scroll_by(delta);
+            needs_update = true;
+        }
+
+        let left_down = window.get_mouse_down(MouseButton::Left);
+        let on_restart = window
+            .get_mouse_pos(MouseMode::Discard)
+            .is_some_and(|pos| is_on_restart_button(cfg, pos));
+        if left_down && !left_was_down {
+            restart_pressed = on_restart;
+        }
+        if restart_pressed && !left_down && on_restart {
+            // Replays start over instead.
+            board = match &mut playback {
+                Some(playback) => playback.restart(),
+                None => board::new_game(cfg),
+            };
+            cfg.scroll = (0, 0);
+            stopwatch = Stopwatch::default();
+            odds = None;
+            inputs = Some(Vec::new());
+            hints_used = 0;
+            hint = None;
+            is_game_over = false;
+            stats_recorded = false;
+            showing_message_since = None;
+            if is_saved {
+                save::delete();
+                is_saved = false;
+            }
+            needs_update = true;
+        }
+        if !left_down {
+            restart_pressed = false;
+        }
+        left_was_down = left_down;
+
+        let mut key_action = keyboard_action(&window, &bindings);
+        if let Some(action) = key_action {
+            let (dx, dy) = match action {
+                BindingAction::CursorUp => (0, -1),
+                BindingAction::CursorDown => (0, 1),
+                BindingAction::CursorLeft => (-1, 0),
+                BindingAction::CursorRight => (1, 0),
+                _ => (0, 0),
+            };
+            match cursor {
+                // The first key only shows the cursor, so nothing happens out of sight.
+                None => {
+                    let (view_cols, view_rows) = cfg.view_size();
+                    cursor = Some((cfg.scroll.0 + view_cols / 2, cfg.scroll.1 + view_rows / 2));
+                    key_action = None;
+                }
+                // It stops at the edges.
+                Some((x, y)) => {
+                    cursor = Some((
+                        x.saturating_add_signed(dx).min(cfg.cell_cols - 1),
+                        y.saturating_add_signed(dy).min(cfg.cell_rows - 1),
+                    ));
+                }
+            }
+            if let Some(cell) = cursor {
+                cfg.scroll_to(cell);
+            }
+            needs_update = true;
+        }
+
         // Skip processing clicks when the game is over.
         let mut was_input = !is_game_over;
+        let changes_before_input = board.changes();
+        let mines_placed_before_input = board.mines_placed();
         'input_block: {
             let accept_input = !is_game_over
                 || showing_message_since
                     .map(|d| Instant::now() - d > Duration::from_secs_f32(1.0))
                     .unwrap_or(false);
             if accept_input {
-                let mut left_click_cell = mouse_left.check(cfg, &window);
-                let mut middle_click_cell = mouse_middle.check(cfg, &window);
-                let mut right_click_cell = mouse_right.check(cfg, &window);
-                if showing_message_since.is_some() && left_click_cell.is_some() {
+                // The minimap covers the cells under it.
+                let mouse_click = mouse.click(&window, |pos| {
+                    cfg.pos_to_cell_f(pos)
+                        .filter(|_| minimap_cell(cfg, pos).is_none())
+                });
+                let mods = bindings::mods_down(&window);
+                let click = mouse_click
+                    .and_then(|(trigger, cell)| Some((bindings.mouse_action(trigger, mods)?, cell)))
+                    .or(key_action.zip(cursor));
+                if showing_message_since.is_some()
+                    && click.is_some_and(|(action, _)| action == BindingAction::Open)
+                {
                     was_input = true; // update window
                     showing_message_since = None;
                     break 'input_block;
                 }
-                if middle_click_cell.is_none()
-                    && ((left_click_cell.is_some() && right_click_cell.is_some())
-                        || (left_click_cell.is_some()
-                            && (window.is_key_down(Key::LeftShift)
-                                || window.is_key_down(Key::RightShift))))
-                {
-                    middle_click_cell = left_click_cell.take();
-                    right_click_cell = None;
+                if playback.is_some() {
+                    was_input = false;
+                    break 'input_block;
                 }
-                if let Some((cell_x, cell_y)) = middle_click_cell {
-                    // Chording/multi-open/whatever-you-want-to-call-it: if there are enough flags, open the cells all around.
-                    match cells[cell_y][cell_x] {
-                        Cell::Unopened | Cell::Flagged => {}
-                        Cell::Opened => {
-                            let mut flag_count = 0;
-                            do_surrounding(cfg, cell_x, cell_y, |sx, sy| {
-                                if cells[sy][sx] == Cell::Flagged {
-                                    flag_count += 1;
-                                }
-                            });
-                            let mine_count = mine_counts[cfg.cell_coords_to_idx(cell_x, cell_y)];
-                            if flag_count == mine_count {
-                                let mut opened_any_mines = false;
-                                do_surrounding(cfg, cell_x, cell_y, |sx, sy| {
-                                    let cell = &mut cells[sy][sx];
-                                    match cell {
-                                        Cell::Unopened => {
-                                            open_cell(
-                                                cfg,
-                                                sx,
-                                                sy,
-                                                &mut cells,
-                                                &mut mine_counts,
-                                                &mut mines,
-                                            );
-                                            if mines[cfg.cell_coords_to_idx(sx, sy)] {
-                                                opened_any_mines = true;
-                                            }
-                                        }
-                                        Cell::Flagged | Cell::Opened => {}
-                                    }
-                                });
-                                move_count += 1;
-                                // Don't return/break so that the board gets updated one last time.
-                                just_lost = opened_any_mines;
-                                just_won = !just_lost && all_safe_cells_opened(cfg, &mines, &cells);
-                                is_game_over = is_game_over || just_lost || just_won;
-                            } else {
-                                play_bell();
-                            }
+                match click {
+                    Some((BindingAction::Chord, (cell_x, cell_y))) => {
+                        if !play(
+                            &mut board,
+                            &mut inputs,
+                            &stopwatch,
+                            Action::Chord(cell_x, cell_y),
+                        ) && board.cell(cell_x, cell_y) == Cell::Opened
+                        {
+                            play_bell();
                         }
                     }
-                } else {
-                    // If the *other* button is clicked, it seems like a misclick.
-                    left_click_cell = left_click_cell.filter(|_| mouse_right.held.is_none());
-                    right_click_cell = right_click_cell.filter(|_| mouse_left.held.is_none());
-                    if left_click_cell.is_some()
-                        && right_click_cell.is_none()
-                        && (window.is_key_down(Key::LeftCtrl) || window.is_key_down(Key::RightCtrl))
-                    {
-                        right_click_cell = left_click_cell.take();
+                    Some((BindingAction::Open, (cell_x, cell_y))) => {
+                        play(
+                            &mut board,
+                            &mut inputs,
+                            &stopwatch,
+                            Action::Open(cell_x, cell_y),
+                        );
                     }
-                    if let Some((cell_x, cell_y)) = left_click_cell {
-                        let cell = &mut cells[cell_y][cell_x];
-                        match cell {
-                            Cell::Unopened => {
-                                if move_count == 0 {
-                                    initialize_mines(cfg, &mut rng, &mut mines, (cell_x, cell_y));
-                                    generate_mine_counts(cfg, &mut mine_counts, &mines);
-                                }
+                    Some((BindingAction::Flag, (cell_x, cell_y))) => {
+                        play(
+                            &mut board,
+                            &mut inputs,
+                            &stopwatch,
+                            Action::Flag(cell_x, cell_y),
+                        );
+                    }
+                    // Mouse buttons bound to anything else do nothing on the board.
+                    _ => was_input = false,
+                }
+            }
+        }
 
-                                open_cell(
-                                    cfg,
-                                    cell_x,
-                                    cell_y,
-                                    &mut cells,
-                                    &mut mine_counts,
-                                    &mut mines,
-                                );
+        if !mines_placed_before_input && board.mines_placed() && board.may_need_guess() {
+            notice = Some(
+                cfg.en_jp(
+                    "No layout that never needs a guess\nturned up, so this one might.",
+                    "運任せなしの配置が見つからなかったので、\n推測が必要になるかもしれません。",
+                )
+                .to_owned(),
+            );
+            needs_update = true;
+        }
 
-                                move_count += 1;
+        // Only once per move, since solving big boards is slow.
+        let changed = board.changes() != changes_before_input;
+        if shared::DEBUG_SOLVER && changed && !board.is_game_over() {
+            let deductions = solver::solve(&board);
+            let mines = deductions.iter().filter(|d| d.is_mine).count();
+            println!(
+                "Solver: {} provably safe, {mines} provably mines.",
+                deductions.len() - mines
+            );
+        }
 
-                                // Don't return/break so that the board gets updated one last time.
-                                just_lost = mines[cfg.cell_coords_to_idx(cell_x, cell_y)];
-                                just_won = !just_lost && all_safe_cells_opened(cfg, &mines, &cells);
-                                is_game_over = is_game_over || just_lost || just_won;
-                            }
-                            Cell::Opened => {}
-                            Cell::Flagged => {}
-                        }
-                    } else if let Some((cell_x, cell_y)) = right_click_cell {
-                        let cell = &mut cells[cell_y][cell_x];
-                        match cell {
-                            Cell::Unopened => {
-                                *cell = Cell::Flagged;
-                            }
-                            Cell::Opened => {}
-                            Cell::Flagged => {
-                                *cell = Cell::Unopened;
-                            }
-                        }
-                    } else {
-                        was_input = false;
+        if let Some(playback) = &mut playback {
+            let now = Instant::now();
+            if playback.advance(now - last_frame, &mut board) {
+                // It might have undone the end of the game.
+                is_game_over = false;
+                showing_message_since = None;
+                needs_update = true;
+            }
+        }
+        last_frame = Instant::now();
+
+        // Don't return/break so that the board gets updated one last time.
+        if !is_game_over && board.is_game_over() {
+            is_game_over = true;
+            just_won = board.state() == GameState::Won;
+            just_lost = board.state() == GameState::Lost;
+            // Replays and known layouts don't count for anything.
+            let key = BoardKey::of_game(cfg, &board).filter(|_| playback.is_none());
+            if is_saved {
+                save::delete();
+                is_saved = false;
+            }
+            if let Some(key) = key.filter(|_| !stats_recorded) {
+                let mut stats = Stats::load();
+                if just_won {
+                    stats.add_win(key, stopwatch.elapsed());
+                } else {
+                    stats.add_loss(key);
+                }
+                if let Err(err) = stats.save() {
+                    eprintln!("Couldn't save the stats: {err}");
+                }
+                stats_recorded = true;
+            }
+            // Wins with help don't count.
+            new_record = None;
+            let key = key.filter(|_| just_won && board.undo_count() == 0 && hints_used == 0);
+            if let Some(key) = key {
+                let mut records = Records::load();
+                let record = Record::new(stopwatch.elapsed(), Metrics::of(&board));
+                new_record = records.add(key, record);
+                if new_record.is_some() {
+                    if let Err(err) = records.save() {
+                        eprintln!("Couldn't save the records: {err}");
                     }
                 }
             }
         }
+        if let Some(playback) = &playback {
+            stopwatch = Stopwatch::with_elapsed(playback.time());
+        } else if board.has_started() && !is_game_over {
+            stopwatch.start();
+        } else {
+            stopwatch.stop();
+        }
+
+        let restart_held = restart_pressed && on_restart;
+        let status = Status {
+            counter: board.mines_left(),
+            seconds: stopwatch.elapsed().as_secs(),
+            face: match board.state() {
+                _ if restart_held => '🙂',
+                GameState::Won => '😎',
+                GameState::Lost => '😵',
+                GameState::InProgress if mouse.left.held.is_some() => '😮',
+                GameState::InProgress => '🙂',
+            },
+            face_pressed: restart_held,
+        };
 
         // Skip updating the buffer until there is input.
         needs_update |= was_input;
+        if was_input {
+            // It's probably stale now.
+            hint = None;
+        }
         if needs_update {
+            let (scroll_x, scroll_y) = cfg.scroll_pos();
             for (i, px) in buffer.iter_mut().enumerate() {
                 let row = i / cfg.buffer_width;
                 let col = i % cfg.buffer_width;
-                *px = if row > cfg.cell_rows * (CELL_SIZE + 1)
-                    || col > cfg.cell_cols * (CELL_SIZE + 1)
+                *px = if row < STATUS_BAR_HEIGHT {
+                    cfg.theme.status_bar
+                } else if row + scroll_y > cfg.board_height() || col + scroll_x > cfg.board_width()
+                {
+                    cfg.theme.oob
+                } else if (row - STATUS_BAR_HEIGHT).is_multiple_of(cfg.cell_size + 1)
+                    || col.is_multiple_of(cfg.cell_size + 1)
                 {
-                    shared::COLOR_OOB
-                } else if row % (CELL_SIZE + 1) == 0 || col % (CELL_SIZE + 1) == 0 {
-                    shared::COLOR_LINE
+                    cfg.theme.line
                 } else {
                     let (cell_x, cell_y) = cfg.pos_to_cell((col, row)).expect("somehow OoB");
-                    match cells[cell_y][cell_x] {
-                        Cell::Unopened => shared::COLOR_UNOPENED,
-                        Cell::Opened => shared::COLOR_OPENED,
-                        Cell::Flagged => shared::COLOR_UNOPENED,
+                    match board.cell(cell_x, cell_y) {
+                        Cell::Unopened => cfg.theme.unopened,
+                        Cell::Opened => cfg.theme.opened,
+                        Cell::Flagged | Cell::Questioned => cfg.theme.unopened,
                     }
                 };
             }
 
-            let mut mines_left = cfg.mine_count;
-            for (cell_y, cell_row) in cells.iter().enumerate() {
-                for (cell_x, &cell) in cell_row.iter().enumerate() {
-                    let i = cfg.cell_coords_to_idx(cell_x, cell_y);
+            // Only what's in view, since huge boards would take ages.
+            let (view_cols, view_rows) = cfg.view_size();
+            for cell_y in cfg.scroll.1..cfg.scroll.1 + view_rows {
+                for cell_x in cfg.scroll.0..cfg.scroll.0 + view_cols {
+                    let cell = board.cell(cell_x, cell_y);
+                    let is_mine = board.is_mine(cell_x, cell_y);
                     match cell {
-                        Cell::Unopened => {
-                            if is_game_over && mines[i] {
+                        Cell::Unopened | Cell::Questioned => {
+                            if is_game_over && is_mine {
                                 draw_char_in_cell(
                                     cfg,
                                     &emoji_font,
                                     '💣',
-                                    shared::COLOR_TEXT_DARK,
+                                    cfg.theme.text_dark,
+                                    cell_x,
+                                    cell_y,
+                                    buffer.as_mut_slice(),
+                                );
+                            } else if cell == Cell::Questioned {
+                                draw_char_in_cell(
+                                    cfg,
+                                    font,
+                                    '?',
+                                    cfg.theme.text_dark,
                                     cell_x,
                                     cell_y,
                                     buffer.as_mut_slice(),
@@ -296,12 +998,12 @@ pub fn run(cfg: &mut Config) -> GameEnd {
                         }
 
                         Cell::Opened => {
-                            if mines[i] {
+                            if is_mine {
                                 draw_char_in_cell(
                                     cfg,
                                     &emoji_font,
                                     '💣',
-                                    shared::COLOR_TEXT_LIGHT,
+                                    cfg.theme.text_light,
                                     cell_x,
                                     cell_y,
                                     buffer.as_mut_slice(),
@@ -309,13 +1011,13 @@ pub fn run(cfg: &mut Config) -> GameEnd {
                                 continue;
                             }
 
-                            let mine_count = mine_counts[i];
+                            let mine_count = board.nearby_mines(cell_x, cell_y);
                             if mine_count > 0 {
                                 draw_char_in_cell(
                                     cfg,
                                     font,
                                     digits[usize::from(mine_count)],
-                                    shared::COLOR_TEXT_LIGHT,
+                                    cfg.number_color(mine_count),
                                     cell_x,
                                     cell_y,
                                     buffer.as_mut_slice(),
@@ -324,15 +1026,14 @@ pub fn run(cfg: &mut Config) -> GameEnd {
                         }
 
                         Cell::Flagged => {
-                            mines_left -= 1;
                             draw_char_in_cell(
                                 cfg,
                                 &emoji_font,
                                 '🚩',
-                                if is_game_over && !mines[i] {
-                                    shared::COLOR_TEXT_WRONG_FLAG
+                                if is_game_over && !is_mine {
+                                    cfg.theme.text_wrong_flag
                                 } else {
-                                    shared::COLOR_TEXT_DARK
+                                    cfg.theme.text_dark
                                 },
                                 cell_x,
                                 cell_y,
@@ -343,29 +1044,137 @@ pub fn run(cfg: &mut Config) -> GameEnd {
                 }
             }
 
+            if show_odds && !is_game_over {
+                if odds.as_ref().map(|&(changes, _)| changes) != Some(board.changes()) {
+                    odds = Some((board.changes(), probability::mine_probabilities(&board)));
+                }
+                let (_, probabilities) = odds.as_ref().expect("just worked out");
+                for cell_y in cfg.scroll.1..cfg.scroll.1 + view_rows {
+                    for cell_x in cfg.scroll.0..cfg.scroll.0 + view_cols {
+                        if let Some(p) = probabilities[cell_y * cfg.cell_cols + cell_x] {
+                            shade_cell(
+                                cfg,
+                                shared::lerp_colors(
+                                    cfg.theme.odds_safe,
+                                    cfg.theme.odds_mine,
+                                    p as f32,
+                                ),
+                                cell_x,
+                                cell_y,
+                                &mut buffer,
+                            );
+                        }
+                    }
+                }
+            }
+
+            if let Some((cell_x, cell_y)) = hint {
+                outline_cell(cfg, cfg.theme.hint, cell_x, cell_y, &mut buffer);
+            }
+            if let Some((cell_x, cell_y)) = cursor {
+                outline_cell(cfg, cfg.theme.cursor, cell_x, cell_y, &mut buffer);
+            }
+
+            draw_minimap(cfg, &board, &mut buffer);
+            draw_status_bar(cfg, &font_en, &emoji_font, status, &mut buffer);
+            status_drawn = Some(status);
+
             if just_won || just_lost {
-                let font = font.as_scaled(CELL_SIZE_F);
+                let font = font.as_scaled(message_font_size(cfg));
+                let mut msg = String::from(if just_won {
+                    cfg.en_jp("You won!", "やった！")
+                } else {
+                    cfg.en_jp("You lost!", "負けました。")
+                });
+                let time = stopwatch.elapsed();
+                let m = Metrics::of(&board);
+                if just_won {
+                    let secs = time.as_secs_f64();
+                    msg += &match cfg.lang {
+                        Lang::En => format!(
+                            "\nTime: {secs:.2}s\n\
+                             3BV: {} ({:.2}/s)\n\
+                             Clicks: {} ({} effective)\n\
+                             Efficiency: {:.0}%\n\
+                             IOE: {:.3}  RQP: {:.1}",
+                            m.bbbv,
+                            m.bbbv_per_sec(time),
+                            m.clicks,
+                            m.effective_clicks,
+                            m.efficiency(),
+                            m.ioe(),
+                            m.rqp(time),
+                        ),
+                        Lang::Jp => format!(
+                            "\nタイム：{secs:.2}秒\n\
+                             3BV：{}（{:.2}/秒）\n\
+                             クリック：{}（有効{}）\n\
+                             効率：{:.0}%\n\
+                             IOE：{:.3}　RQP：{:.1}",
+                            m.bbbv,
+                            m.bbbv_per_sec(time),
+                            m.clicks,
+                            m.effective_clicks,
+                            m.efficiency(),
+                            m.ioe(),
+                            m.rqp(time),
+                        ),
+                    };
+                } else {
+                    msg += &match cfg.lang {
+                        Lang::En => format!("\n3BV: {}\nClicks: {}", m.bbbv, m.clicks),
+                        Lang::Jp => format!("\n3BV：{}\nクリック：{}", m.bbbv, m.clicks),
+                    };
+                }
+                if let Some(place) = new_record {
+                    msg += &match cfg.lang {
+                        Lang::En => format!("\nNew record! (#{})", place + 1),
+                        Lang::Jp => format!("\n新記録！（{}位）", place + 1),
+                    };
+                }
+                if hints_used > 0 {
+                    msg += &match cfg.lang {
+                        Lang::En => format!("\nHints used: {hints_used}"),
+                        Lang::Jp => format!("\nヒント：{hints_used}回"),
+                    };
+                }
+                if board.undo_count() > 0 {
+                    msg += cfg.en_jp("\n(Undone: no record)", "\n（元に戻したため記録なし）");
+                }
+                show_message(cfg, &msg, font, &mut buffer);
+                showing_message_since = Some(Instant::now());
+                just_won = false;
+                just_lost = false;
+            } else if let Some(msg) = notice.take() {
                 show_message(
                     cfg,
-                    if just_won {
-                        cfg.en_jp("You won!", "やった！")
-                    } else {
-                        cfg.en_jp("You lost!", "負けました。")
-                    },
-                    font,
+                    &msg,
+                    font.as_scaled(message_font_size(cfg)),
                     &mut buffer,
                 );
                 showing_message_since = Some(Instant::now());
-                just_won = false;
-                just_lost = false;
             }
 
+            let seed = match board.seed() {
+                Some(seed) => format!(" - {}{seed}", cfg.en_jp("Seed: ", "シード：")),
+                None => String::new(),
+            };
+            let replay = if playback.is_some() {
+                cfg.en_jp(" - Replay", " - リプレイ")
+            } else {
+                ""
+            };
             window.set_title(&format!(
-                "{} - {mines_left}💣",
+                "{}{seed}{replay}",
                 cfg.en_jp("Minesweeper", "マインスイーパ")
             ));
 
             needs_update = false;
+        } else if status_drawn != Some(status) && showing_message_since.is_none() {
+            // Just the timer ticking, or the like. (Skipped under a message
+            // so it doesn't get drawn over.)
+            draw_status_bar(cfg, &font_en, &emoji_font, status, &mut buffer);
+            status_drawn = Some(status);
         }
 
         window
@@ -373,194 +1182,28 @@ pub fn run(cfg: &mut Config) -> GameEnd {
             .unwrap();
     }
 
-    GameEnd::Quit
-}
-
-fn initialize_mines(
-    cfg: &Config,
-    rng: &mut fastrand::Rng,
-    mines: &mut [bool],
-    first_click: (usize, usize),
-) {
-    let (click_x, click_y) = first_click;
-    let mut safe_zone = Vec::with_capacity(SAFE_CELLS_FOR_FIRST_CLICK);
-    safe_zone.push(cfg.cell_coords_to_idx(click_x, click_y));
-    do_surrounding(cfg, click_x, click_y, |sx, sy| {
-        safe_zone.push(cfg.cell_coords_to_idx(sx, sy))
-    });
-    let random_indices = rng.choose_multiple(
-        0..cfg.cell_rows * cfg.cell_cols,
-        cfg.mine_count + SAFE_CELLS_FOR_FIRST_CLICK,
-    );
-    let mut mines_placed = 0;
-    mines.fill(false);
-    if cfg.mine_count > 0 {
-        for i in random_indices {
-            if safe_zone.contains(&i) {
-                continue;
-            }
-            mines[i] = true;
-            mines_placed += 1;
-            if mines_placed == cfg.mine_count {
-                break;
-            }
-        }
-    }
-    debug_assert_eq!(cfg.mine_count, mines.iter().filter(|&&b| b).count());
-}
-
-fn generate_mine_counts(cfg: &Config, mine_counts: &mut [u8], mines: &[bool]) {
-    for cell_y in 0..cfg.cell_rows {
-        for cell_x in 0..cfg.cell_cols {
-            mine_counts[cfg.cell_coords_to_idx(cell_x, cell_y)] =
-                count_nearby_mines(cfg, cell_x, cell_y, mines);
+    // Keep the game for next time, unless there's nothing worth keeping.
+    if board.has_started() && !board.is_game_over() && playback.is_none() {
+        if let Err(err) = save_game(&board, &stopwatch, hints_used, &inputs) {
+            eprintln!("Couldn't save the game: {err}");
         }
     }
-}
-
-fn count_nearby_mines(cfg: &Config, cell_x: usize, cell_y: usize, mines: &[bool]) -> u8 {
-    let mut count = 0;
-    do_surrounding(cfg, cell_x, cell_y, |sx, sy| {
-        if mines[cfg.cell_coords_to_idx(sx, sy)] {
-            count += 1;
-        }
-    });
-    count
-}
 
-/// Opens the cell. If it's a 0, auto-opens the surrounding cells, etc. If it's
-/// a mine, tries to move it to a neighboring cell, if it wouldn't change the
-/// revealed information, to help reduce the need for the player to guess.
-fn open_cell(
-    cfg: &Config,
-    sx: usize,
-    sy: usize,
-    cells: &mut Vec<Vec<Cell>>,
-    mine_counts: &mut [u8],
-    mines: &mut Box<[bool]>,
-) {
-    let mut cells_to_process = Vec::new();
-    if cells[sy][sx] == Cell::Unopened {
-        cells_to_process.push((sx, sy));
-    }
-    while let Some((x, y)) = cells_to_process.pop() {
-        if mines[cfg.cell_coords_to_idx(x, y)] {
-            _ = try_move_mine(cfg, x, y, cells, mine_counts, mines);
-        }
-        cells[y][x] = Cell::Opened;
-        if mine_counts[cfg.cell_coords_to_idx(x, y)] == 0 {
-            do_surrounding(cfg, x, y, |ssx, ssy| {
-                if cells[ssy][ssx] == Cell::Unopened {
-                    cells_to_process.push((ssx, ssy));
-                }
-            });
-        }
-    }
+    GameEnd::Quit
 }
 
-fn try_move_mine(
-    cfg: &Config,
-    cell_x: usize,
-    cell_y: usize,
-    cells: &Vec<Vec<Cell>>,
-    mine_counts: &mut [u8],
-    mines: &mut Box<[bool]>,
+/// Does something to the board, keeping track of it for the replay. Returns
+/// whether anything happened.
+fn play(
+    board: &mut Board,
+    inputs: &mut Option<Vec<Input>>,
+    stopwatch: &Stopwatch,
+    action: Action,
 ) -> bool {
-    let mut found_solution = false;
-    let mut new_mines = vec![false; mines.len()].into_boxed_slice();
-    do_surrounding(cfg, cell_x, cell_y, |sx, sy| {
-        // Can't move a mine onto a mine.
-        if found_solution || mines[cfg.cell_coords_to_idx(sx, sy)] {
-            if !found_solution {
-                println!("Anti-guess: rejected {sx},{sy} because there's a mine there.");
-            }
-            return;
-        }
-
-        new_mines.clone_from_slice(mines);
-        new_mines[cfg.cell_coords_to_idx(cell_x, cell_y)] = false;
-        new_mines[cfg.cell_coords_to_idx(sx, sy)] = true;
-
-        let mut any_changes_to_revealed_numbers = false;
-        let mut numbers_that_would_be_changed = Vec::new();
-        for (x, y) in [(cell_x, cell_y), (sx, sy)] {
-            do_surrounding(cfg, x, y, |ssx, ssy| {
-                if cells[ssy][ssx] == Cell::Opened
-                    && mine_counts[cfg.cell_coords_to_idx(ssx, ssy)]
-                        != count_nearby_mines(cfg, ssx, ssy, &new_mines)
-                {
-                    any_changes_to_revealed_numbers = true;
-                    if shared::DEBUG_ANTI_GUESS {
-                        numbers_that_would_be_changed.push((
-                            ssx,
-                            ssy,
-                            mine_counts[cfg.cell_coords_to_idx(ssx, ssy)],
-                        ));
-                    }
-                }
-            });
-        }
-        if any_changes_to_revealed_numbers {
-            if shared::DEBUG_ANTI_GUESS {
-                println!(
-                    "Anti-guess: rejected {sx},{sy} because it would change the {}.",
-                    numbers_that_would_be_changed
-                        .into_iter()
-                        .map(|(_ssx, _ssy, num)| format!("{num}"))
-                        .collect::<Vec<_>>()
-                        .join(", ")
-                );
-            }
-            return;
-        }
-
-        found_solution = true;
-    });
-
-    if !found_solution {
-        return false;
-    }
-
-    if shared::DEBUG_ANTI_GUESS {
-        let differences: Vec<_> = (0..cfg.cell_rows)
-            .flat_map(|y| (0..cfg.cell_cols).map(move |x| (x, y)))
-            .filter(|&(x, y)| {
-                mines[cfg.cell_coords_to_idx(x, y)] != new_mines[cfg.cell_coords_to_idx(x, y)]
-                    && (x, y) != (cell_x, cell_y)
-            })
-            .collect();
-        let (new_x, new_y) = match *differences {
-            [coords] => coords,
-            [] => panic!("Moved a mine, but the board is identical?"),
-            [_, _, ..] => panic!("Moved a mine, but multiple cells changed?"),
-        };
-        println!("Anti-guess: moved a mine from {cell_x},{cell_y} to {new_x},{new_y}.");
-    }
-    *mines = new_mines;
-    // Regenerate the whole board so we can notice bugs more easily.
-    generate_mine_counts(cfg, mine_counts, mines);
-    true
-}
-
-fn all_safe_cells_opened(cfg: &Config, mines: &[bool], cells: &Vec<Vec<Cell>>) -> bool {
-    !cells
-        .iter()
-        .enumerate()
-        .flat_map(|(y, row)| row.iter().enumerate().map(move |(x, _)| (x, y)))
-        .filter(|&(x, y)| !mines[cfg.cell_coords_to_idx(x, y)])
-        .any(|(x, y)| {
-            if cells[y][x] == Cell::Unopened {
-                if shared::DEBUG_PRINTS {
-                    println!("Some cells are still unopened (e.g., {x},{y}).");
-                }
-                true
-            } else {
-                false
-            }
-        })
+    replay::play(board, inputs, stopwatch.elapsed(), action)
 }
 
-fn show_message<F, FS>(cfg: &Config, msg: &str, font: FS, buffer: &mut [u32])
+pub fn show_message<F, FS>(cfg: &Config, msg: &str, font: FS, buffer: &mut [u32])
 where
     F: Font,
     FS: ScaleFont<F>,
@@ -568,21 +1211,24 @@ where
     let mut glyphs = Vec::new();
     let glyphs_size =
         text::layout_paragraph(&font, cfg.buffer_width as f32 / 2.0, msg, &mut glyphs);
-    let left_margin = (cfg.buffer_width - glyphs_size.x as usize) / 2;
-    let top_margin = (cfg.buffer_height - glyphs_size.y as usize) / 2;
+    let left_margin = cfg.buffer_width.saturating_sub(glyphs_size.x as usize) / 2;
+    let top_margin = cfg.buffer_height.saturating_sub(glyphs_size.y as usize) / 2;
     // Draw box with outline
     {
         let box_padding_left_right = font.scale().x as usize;
         let box_padding_top_bottom = font.scale().y as usize;
-        let box_left = left_margin - box_padding_left_right;
-        let box_top = top_margin - box_padding_top_bottom;
-        let box_width = box_padding_left_right * 2 + glyphs_size.x as usize;
-        let box_height = box_padding_top_bottom * 2 + glyphs_size.y as usize;
+        let box_left = left_margin.saturating_sub(box_padding_left_right);
+        let box_top = top_margin.saturating_sub(box_padding_top_bottom);
+        // Long messages get cut off rather than drawn out of bounds.
+        let box_width = (box_padding_left_right * 2 + glyphs_size.x as usize)
+            .min(cfg.buffer_width - 1 - box_left);
+        let box_height = (box_padding_top_bottom * 2 + glyphs_size.y as usize)
+            .min(cfg.buffer_height - 1 - box_top);
         let outline = 2;
         shared::draw_rectangle(
             IVec2::new(box_left as i32, box_top as i32),
             IVec2::new(box_width as i32, box_height as i32),
-            shared::COLOR_MESSAGE_BORDER,
+            cfg.theme.message_border,
             buffer,
             cfg.buffer_width,
         );
@@ -592,7 +1238,7 @@ where
                 (box_width - outline * 2) as i32,
                 (box_height - outline * 2) as i32,
             ),
-            shared::COLOR_MESSAGE_BOX,
+            cfg.theme.message_box,
             buffer,
             cfg.buffer_width,
         );
@@ -601,79 +1247,380 @@ where
         glyphs.into_iter(),
         IVec2::new(left_margin as i32, top_margin as i32),
         &font,
-        shared::COLOR_MESSAGE_TEXT,
+        cfg.theme.message_text,
         buffer,
         cfg.buffer_width,
     );
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
-#[repr(u8)]
-enum Cell {
-    Unopened,
-    Opened,
-    Flagged,
+/// What's shown in the status bar.
+#[derive(Copy, Clone, PartialEq)]
+pub struct Status {
+    /// Mines left, or the score on boards with no edges.
+    pub counter: isize,
+    pub seconds: u64,
+    pub face: char,
+    pub face_pressed: bool,
+}
+
+const STATUS_BAR_PADDING: usize = 4;
+const COUNTER_FONT_SIZE: f32 = 20.0;
+/// The restart button stays the same size however big the cells are.
+const RESTART_BUTTON_SIZE: usize = DEFAULT_CELL_SIZE;
+
+/// The top left of the restart button.
+fn restart_button_pos(cfg: &Config) -> (usize, usize) {
+    (
+        (cfg.buffer_width - RESTART_BUTTON_SIZE) / 2,
+        (STATUS_BAR_HEIGHT - RESTART_BUTTON_SIZE) / 2,
+    )
+}
+
+pub fn is_on_restart_button(cfg: &Config, (x, y): (f32, f32)) -> bool {
+    let (left, top) = restart_button_pos(cfg);
+    let (x, y) = (x as usize, y as usize);
+    (left..left + RESTART_BUTTON_SIZE).contains(&x) && (top..top + RESTART_BUTTON_SIZE).contains(&y)
+}
+
+/// Messages grow and shrink with the cells, but stay readable.
+pub fn message_font_size(cfg: &Config) -> f32 {
+    cfg.cell_size_f().clamp(16.0, 40.0)
+}
+
+/// Draws the mine counter, restart button and timer.
+pub fn draw_status_bar(
+    cfg: &Config,
+    font: impl Font,
+    emoji_font: impl Font,
+    status: Status,
+    buffer: &mut [u32],
+) {
+    shared::draw_rectangle(
+        IVec2::ZERO,
+        IVec2::new(cfg.buffer_width as i32 - 1, STATUS_BAR_HEIGHT as i32 - 1),
+        cfg.theme.status_bar,
+        buffer,
+        cfg.buffer_width,
+    );
+
+    // Classic counters only have three digits, but huge boards need more
+    // for their mines.
+    let counters = [
+        (
+            format!("{:03}", status.counter.max(-99)),
+            STATUS_BAR_PADDING,
+        ),
+        (format!("{:03}", status.seconds.min(999)), 0),
+    ];
+    let font = font.as_scaled(COUNTER_FONT_SIZE);
+    for (i, (text, mut left)) in counters.into_iter().enumerate() {
+        let mut glyphs = Vec::new();
+        let size = text::layout_paragraph(&font, f32::INFINITY, &text, &mut glyphs);
+        let box_size = size + IVec2::splat(STATUS_BAR_PADDING as i32 * 2);
+        if i == 1 {
+            left = cfg.buffer_width - STATUS_BAR_PADDING - box_size.x as usize;
+        }
+        let top = (STATUS_BAR_HEIGHT as i32 - box_size.y) / 2;
+        shared::draw_rectangle(
+            IVec2::new(left as i32, top),
+            box_size,
+            cfg.theme.counter,
+            buffer,
+            cfg.buffer_width,
+        );
+        text::draw_glyphs(
+            glyphs.into_iter(),
+            IVec2::new(left as i32, top) + IVec2::splat(STATUS_BAR_PADDING as i32),
+            &font,
+            cfg.theme.counter_text,
+            buffer,
+            cfg.buffer_width,
+        );
+    }
+
+    let (left, top) = restart_button_pos(cfg);
+    shared::draw_rectangle(
+        IVec2::new(left as i32, top as i32),
+        IVec2::splat(RESTART_BUTTON_SIZE as i32),
+        if status.face_pressed {
+            cfg.theme.opened
+        } else {
+            cfg.theme.unopened
+        },
+        buffer,
+        cfg.buffer_width,
+    );
+    draw_char_centered(
+        cfg,
+        emoji_font,
+        status.face,
+        cfg.theme.text_dark,
+        (left, top, RESTART_BUTTON_SIZE),
+        buffer,
+    );
 }
 
-struct CellsMouseState {
+/// The mouse buttons, for clicking on cells. `C` is a cell's coords.
+pub struct CellsMouse<C> {
+    pub left: CellsMouseState<C>,
+    pub middle: CellsMouseState<C>,
+    pub right: CellsMouseState<C>,
+}
+impl<C: Copy + PartialEq> CellsMouse<C> {
+    pub fn new() -> Self {
+        Self {
+            left: CellsMouseState::new(MouseButton::Left),
+            middle: CellsMouseState::new(MouseButton::Middle),
+            right: CellsMouseState::new(MouseButton::Right),
+        }
+    }
+
+    /// The button (or pair of them) that clicked a cell this frame, if any.
+    pub fn click(
+        &mut self,
+        window: &Window,
+        pos_to_cell: impl Fn((f32, f32)) -> Option<C>,
+    ) -> Option<(Trigger, C)> {
+        let left_click_cell = self.left.check(window, &pos_to_cell);
+        let middle_click_cell = self.middle.check(window, &pos_to_cell);
+        let right_click_cell = self.right.check(window, &pos_to_cell);
+        if let Some(cell) = middle_click_cell {
+            Some((Trigger::Mouse(MouseButton::Middle), cell))
+        } else if let (Some(cell), Some(_)) = (left_click_cell, right_click_cell) {
+            Some((Trigger::BothButtons, cell))
+        } else {
+            // If the *other* button is held, it seems like a misclick.
+            let left = left_click_cell.filter(|_| self.right.held.is_none());
+            let right = right_click_cell.filter(|_| self.left.held.is_none());
+            left.map(|cell| (Trigger::Mouse(MouseButton::Left), cell))
+                .or(right.map(|cell| (Trigger::Mouse(MouseButton::Right), cell)))
+        }
+    }
+}
+
+pub struct CellsMouseState<C> {
     button: MouseButton,
-    held: Option<(usize, usize)>,
+    pub held: Option<C>,
+    /// Whether this press turned into something else, like panning, and
+    /// shouldn't click when it's released.
+    cancelled: bool,
 }
-impl CellsMouseState {
-    fn check(&mut self, cfg: &Config, window: &Window) -> Option<(usize, usize)> {
+impl<C: Copy + PartialEq> CellsMouseState<C> {
+    fn new(button: MouseButton) -> Self {
+        Self {
+            button,
+            held: None,
+            cancelled: false,
+        }
+    }
+
+    pub fn cancel(&mut self) {
+        self.held = None;
+        self.cancelled = true;
+    }
+
+    fn check(
+        &mut self,
+        window: &Window,
+        pos_to_cell: impl Fn((f32, f32)) -> Option<C>,
+    ) -> Option<C> {
         if window.get_mouse_down(self.button) {
-            if self.held.is_some() {
+            if self.held.is_some() || self.cancelled {
                 // The mouse was clicked in a previous frame. We're waiting for it to be released.
             } else if let Some(pos) = window.get_mouse_pos(MouseMode::Discard) {
-                self.held = cfg.pos_to_cell_f(pos);
+                self.held = pos_to_cell(pos);
             }
             return None;
         }
-        if let Some((cell_x, cell_y)) = self.held {
+        self.cancelled = false;
+        if let Some(cell) = self.held {
             self.held = None;
-            if let Some((new_cell_x, new_cell_y)) = window
+            // Letting go somewhere else changes your mind.
+            let new_cell = window
                 .get_mouse_pos(MouseMode::Discard)
-                .and_then(|pos| cfg.pos_to_cell_f(pos))
-            {
-                if cell_x == new_cell_x && cell_y == new_cell_y {
-                    return Some((cell_x, cell_y));
-                }
-            }
-            return None;
+                .and_then(pos_to_cell);
+            return Some(cell).filter(|&cell| new_cell == Some(cell));
         }
         None
     }
 }
 
-#[inline]
-fn do_surrounding(cfg: &Config, cell_x: usize, cell_y: usize, mut f: impl FnMut(usize, usize)) {
-    if cell_x > 0 {
-        if cell_y > 0 {
-            f(cell_x - 1, cell_y - 1);
+/// Panning by dragging with the right button, once it's gone far enough to
+/// not be a flag. `V` is where the view is.
+#[derive(Default)]
+pub struct RightDrag<V> {
+    /// Where the drag started, and the view then.
+    start: Option<((f32, f32), V)>,
+    is_panning: bool,
+    was_down: bool,
+}
+impl<V: Copy> RightDrag<V> {
+    /// While panning, returns where the view was when the drag started, and
+    /// how many cells to move it from there.
+    pub fn update(
+        &mut self,
+        window: &Window,
+        cell_size: usize,
+        view: V,
+        can_pan: bool,
+    ) -> Option<(V, (isize, isize))> {
+        let down = window.get_mouse_down(MouseButton::Right);
+        let mouse_pos = window.get_mouse_pos(MouseMode::Discard);
+        if down && !self.was_down && can_pan {
+            self.start = mouse_pos.map(|pos| (pos, view));
+        } else if !down {
+            self.start = None;
+            self.is_panning = false;
         }
-        f(cell_x - 1, cell_y);
-        if cell_y < cfg.cell_rows - 1 {
-            f(cell_x - 1, cell_y + 1);
+        self.was_down = down;
+
+        let (((from_x, from_y), view), (x, y)) = self.start.zip(mouse_pos)?;
+        self.is_panning |= (x - from_x).abs().max((y - from_y).abs()) >= PAN_START_DISTANCE;
+        if !self.is_panning {
+            return None;
         }
+        let cells = |pixels: f32| -(pixels / (cell_size + 1) as f32).round() as isize;
+        Some((view, (cells(x - from_x), cells(y - from_y))))
     }
-    {
-        if cell_y > 0 {
-            f(cell_x, cell_y - 1);
-        }
-        // Obviously no need to do f(cell_x, cell_y)
-        if cell_y < cfg.cell_rows - 1 {
-            f(cell_x, cell_y + 1);
-        }
+}
+
+/// What the wheel did this frame.
+pub enum Wheel {
+    /// With Ctrl, whether to make the cells bigger or smaller.
+    Zoom(bool),
+    /// How many cells to scroll by. Shift scrolls sideways.
+    Scroll(isize, isize),
+}
+
+pub fn read_wheel(window: &Window) -> Option<Wheel> {
+    let (scroll_x, scroll_y) = window.get_scroll_wheel()?;
+    let cells = |amount: f32| -(amount * WHEEL_SCROLL_CELLS).round() as isize;
+    match bindings::mods_down(window) {
+        minifb::MENU_KEY_CTRL if scroll_y != 0.0 => Some(Wheel::Zoom(scroll_y > 0.0)),
+        0 => Some(Wheel::Scroll(cells(scroll_x), cells(scroll_y))),
+        minifb::MENU_KEY_SHIFT => Some(Wheel::Scroll(cells(scroll_y), 0)),
+        _ => None,
     }
-    if cell_x < cfg.cell_cols - 1 {
-        if cell_y > 0 {
-            f(cell_x + 1, cell_y - 1);
-        }
-        f(cell_x + 1, cell_y);
-        if cell_y < cfg.cell_rows - 1 {
-            f(cell_x + 1, cell_y + 1);
+}
+
+/// The cell size after one notch of zooming.
+pub fn zoomed(cell_size: usize, grow: bool) -> usize {
+    if grow {
+        cell_size + ZOOM_STEP
+    } else {
+        cell_size.saturating_sub(ZOOM_STEP)
+    }
+    .clamp(MIN_CELL_SIZE, MAX_CELL_SIZE)
+}
+
+/// How many cells the scroll keys move the view by, if one was pressed. It's
+/// half a window at a time, so there's something to go by.
+pub fn scroll_keys(
+    window: &Window,
+    bindings: &Bindings,
+    (view_cols, view_rows): (usize, usize),
+) -> Option<(isize, isize)> {
+    let action = bindings.pressed(
+        window,
+        &[
+            BindingAction::ScrollUp,
+            BindingAction::ScrollDown,
+            BindingAction::ScrollLeft,
+            BindingAction::ScrollRight,
+        ],
+    )?;
+    let (step_x, step_y) = (
+        (view_cols / 2).max(1) as isize,
+        (view_rows / 2).max(1) as isize,
+    );
+    Some(match action {
+        BindingAction::ScrollUp => (0, -step_y),
+        BindingAction::ScrollDown => (0, step_y),
+        BindingAction::ScrollLeft => (-step_x, 0),
+        _ => (step_x, 0),
+    })
+}
+
+/// Where the minimap goes, and how many pixels a cell is in it, if the board
+/// is too big to see all at once.
+fn minimap_layout(cfg: &Config) -> Option<(IVec2, IVec2, f32)> {
+    if !cfg.is_scrollable() {
+        return None;
+    }
+    // Small windows get small minimaps, so there's still room to play.
+    let longest = MINIMAP_SIZE
+        .min(cfg.buffer_width / 3)
+        .min((cfg.buffer_height - STATUS_BAR_HEIGHT) / 3);
+    let scale = longest as f32 / cfg.cell_cols.max(cfg.cell_rows) as f32;
+    let size = IVec2::new(
+        ((cfg.cell_cols as f32 * scale) as i32).max(1),
+        ((cfg.cell_rows as f32 * scale) as i32).max(1),
+    );
+    let top_left = IVec2::new(cfg.buffer_width as i32, cfg.buffer_height as i32)
+        - size
+        - IVec2::splat(MINIMAP_MARGIN as i32);
+    Some((top_left, size, scale))
+}
+
+/// The cell under a point on the minimap, if it's on the minimap.
+fn minimap_cell(cfg: &Config, (x, y): (f32, f32)) -> Option<(usize, usize)> {
+    let (top_left, size, scale) = minimap_layout(cfg)?;
+    let (x, y) = (x - top_left.x as f32, y - top_left.y as f32);
+    if x < 0.0 || y < 0.0 || x >= size.x as f32 || y >= size.y as f32 {
+        return None;
+    }
+    Some((
+        ((x / scale) as usize).min(cfg.cell_cols - 1),
+        ((y / scale) as usize).min(cfg.cell_rows - 1),
+    ))
+}
+
+/// Draws the whole board small in the corner, with what's opened and the
+/// part in view.
+fn draw_minimap(cfg: &Config, board: &Board, buffer: &mut [u32]) {
+    let Some((top_left, size, scale)) = minimap_layout(cfg) else {
+        return;
+    };
+    shared::draw_rectangle(
+        top_left - IVec2::ONE,
+        size + IVec2::ONE,
+        cfg.theme.message_border,
+        buffer,
+        cfg.buffer_width,
+    );
+    for y in 0..size.y {
+        let cell_y = ((y as f32 / scale) as usize).min(cfg.cell_rows - 1);
+        let row_start_idx = (top_left.y + y) as usize * cfg.buffer_width;
+        for x in 0..size.x {
+            let cell_x = ((x as f32 / scale) as usize).min(cfg.cell_cols - 1);
+            buffer[row_start_idx + (top_left.x + x) as usize] = match board.cell(cell_x, cell_y) {
+                Cell::Opened => cfg.theme.opened,
+                _ => cfg.theme.unopened,
+            };
         }
     }
+
+    // The view, as an outline.
+    let (view_cols, view_rows) = cfg.view_size();
+    let to_minimap = |cells: usize| (cells as f32 * scale) as i32;
+    let view_top_left = top_left + IVec2::new(to_minimap(cfg.scroll.0), to_minimap(cfg.scroll.1));
+    let view_size = IVec2::new(to_minimap(view_cols), to_minimap(view_rows))
+        .min(size - (view_top_left - top_left) - IVec2::ONE)
+        .max(IVec2::ONE);
+    for (offset, line_size) in [
+        (IVec2::ZERO, IVec2::new(view_size.x, 0)),
+        (IVec2::new(0, view_size.y), IVec2::new(view_size.x, 0)),
+        (IVec2::ZERO, IVec2::new(0, view_size.y)),
+        (IVec2::new(view_size.x, 0), IVec2::new(0, view_size.y)),
+    ] {
+        shared::draw_rectangle(
+            view_top_left + offset,
+            line_size,
+            cfg.theme.cursor,
+            buffer,
+            cfg.buffer_width,
+        );
+    }
 }
 
 /// Draws a char at x,y in the (flat) buffer.
@@ -686,26 +1633,149 @@ fn draw_char_in_cell(
     cell_y: usize,
     buffer: &mut [u32],
 ) {
-    let board_x = cell_x * (CELL_SIZE + 1);
-    let board_y = cell_y * (CELL_SIZE + 1);
-    let glyph = font.glyph_id(c).with_scale(CELL_SIZE_F);
+    let Some((board_x, board_y)) = cfg.cell_to_pos((cell_x, cell_y)) else {
+        return;
+    };
+    draw_char_centered(
+        cfg,
+        font,
+        c,
+        color,
+        (board_x + 1, board_y + 1, cfg.cell_size),
+        buffer,
+    );
+}
+
+/// Draws a char in the middle of the `size`-pixel square with its top left at
+/// `left`,`top`. The glyph is drawn at that size, so it's sharp at any zoom.
+pub fn draw_char_centered(
+    cfg: &Config,
+    font: impl Font,
+    c: char,
+    color: u32,
+    (left, top, size): (usize, usize, usize),
+    buffer: &mut [u32],
+) {
+    let size = size as f32;
+    let glyph = font.glyph_id(c).with_scale(size);
     let outlined = font.outline_glyph(glyph).expect("couldn't outline glyph");
-    let offset_x: usize = ((CELL_SIZE_F - outlined.px_bounds().width()) * 0.5) as usize + 1;
-    let offset_y: usize = ((CELL_SIZE_F - outlined.px_bounds().height()) * 0.5) as usize + 1;
+    let offset_x: usize = ((size - outlined.px_bounds().width()) * 0.5) as usize;
+    let offset_y: usize = ((size - outlined.px_bounds().height()) * 0.5) as usize;
     outlined.draw(|x, y, c| {
         let mut x: usize = x.try_into().unwrap();
-        x += board_x;
+        x += left;
         x += offset_x;
         let mut y: usize = y.try_into().unwrap();
-        y += board_y;
+        y += top;
         y += offset_y;
+        // Cells can hang off the edge of the window.
+        if x >= cfg.buffer_width || y >= cfg.buffer_height {
+            return;
+        }
         let i = y * cfg.buffer_width + x;
         // Sometimes c is > 1.0 🤷
         buffer[i] = shared::lerp_colors(buffer[i], color, f32::min(c, 1.0));
     });
 }
 
-fn play_bell() {
+/// Blends a color over the inside of a cell.
+fn shade_cell(cfg: &Config, color: u32, cell_x: usize, cell_y: usize, buffer: &mut [u32]) {
+    let Some((board_x, board_y)) = cfg.cell_to_pos((cell_x, cell_y)) else {
+        return;
+    };
+    let (board_x, board_y) = (board_x + 1, board_y + 1);
+    let right = (board_x + cfg.cell_size).min(cfg.buffer_width);
+    for y in board_y..(board_y + cfg.cell_size).min(cfg.buffer_height) {
+        let row_start_idx = y * cfg.buffer_width;
+        for px in buffer
+            .get_mut(row_start_idx + board_x..row_start_idx + right)
+            .unwrap_or_default()
+        {
+            *px = shared::lerp_colors(*px, color, 0.5);
+        }
+    }
+}
+
+/// Draws a thick border just inside a cell.
+fn outline_cell(cfg: &Config, color: u32, cell_x: usize, cell_y: usize, buffer: &mut [u32]) {
+    if let Some((board_x, board_y)) = cfg.cell_to_pos((cell_x, cell_y)) {
+        outline_square(cfg, color, (board_x + 1, board_y + 1), buffer);
+    }
+}
+
+/// Draws a thick border just inside the cell-sized square with its top left
+/// at `board_x`,`board_y`.
+pub fn outline_square(
+    cfg: &Config,
+    color: u32,
+    (board_x, board_y): (usize, usize),
+    buffer: &mut [u32],
+) {
+    const THICKNESS: usize = 3;
+    let size = cfg.cell_size;
+    for y in board_y..(board_y + size).min(cfg.buffer_height) {
+        for x in board_x..(board_x + size).min(cfg.buffer_width) {
+            let is_edge = x < board_x + THICKNESS
+                || x >= board_x + size - THICKNESS
+                || y < board_y + THICKNESS
+                || y >= board_y + size - THICKNESS;
+            if is_edge {
+                buffer[y * cfg.buffer_width + x] = color;
+            }
+        }
+    }
+}
+
+/// Lists the bindings for some actions, for a help message. It's made from
+/// the bindings, so it's right after they change.
+pub fn controls_help(cfg: &Config, bindings: &Bindings, actions: &[BindingAction]) -> String {
+    let mut help = cfg.en_jp("Controls:", "操作：").to_owned();
+    for &action in actions {
+        help += &format!(
+            "\n{}{}{}",
+            action.name(cfg.lang),
+            cfg.en_jp(": ", "："),
+            bindings.describe(action)
+        );
+    }
+    help
+}
+
+/// Checks the keys for playing without a mouse.
+pub fn keyboard_action(window: &Window, bindings: &Bindings) -> Option<BindingAction> {
+    bindings.pressed(
+        window,
+        &[
+            BindingAction::CursorUp,
+            BindingAction::CursorDown,
+            BindingAction::CursorLeft,
+            BindingAction::CursorRight,
+            BindingAction::Open,
+            BindingAction::Flag,
+            BindingAction::Chord,
+        ],
+    )
+}
+
+/// Checks for menu shortcuts by hand, since minifb only has real menus (which
+/// handle their own shortcuts) on Windows and macOS.
+pub fn pressed_menu_shortcut(
+    window: &Window,
+    bindings: &Bindings,
+    menu_actions: &[(usize, BindingAction)],
+) -> Option<usize> {
+    if cfg!(any(target_os = "windows", target_os = "macos")) {
+        return None;
+    }
+    let actions: Vec<BindingAction> = menu_actions.iter().map(|&(_, action)| action).collect();
+    let action = bindings.pressed(window, &actions)?;
+    menu_actions
+        .iter()
+        .find(|m| m.1 == action)
+        .map(|&(id, _)| id)
+}
+
+pub fn play_bell() {
     use std::io::Write;
     let mut stdout = std::io::stdout();
     stdout.write_all(b"\x07").unwrap();
@@ -0,0 +1,297 @@
+//! Boards with no edges, played until you open a mine.
+//!
+//! The board is split into square chunks. Each one gets its mines from the
+//! seed and its own position the first time anything needs them, so the same
+//! seed and first click always give the same board, however it's explored.
+//! Cells are numbered outwards from anywhere, with negative coordinates for
+//! up and left.
+
+use std::collections::HashMap;
+
+use crate::board::{Cell, GameState};
+use crate::shared::Config;
+
+/// The width and height of a chunk, in cells.
+pub const CHUNK_SIZE: i64 = 32;
+const CHUNK_AREA: usize = (CHUNK_SIZE * CHUNK_SIZE) as usize;
+/// Any sparser, and the empty areas could go on forever.
+pub const MIN_DENSITY: f64 = 0.15;
+
+struct Chunk {
+    mines: Box<[bool]>,
+    cells: Box<[Cell]>,
+    /// The number of nearby mines, for opened cells.
+    mine_counts: Box<[u8]>,
+}
+
+pub struct InfiniteBoard {
+    seed: u64,
+    /// How many of each chunk's cells are mines.
+    chunk_mines: usize,
+    chunks: HashMap<(i64, i64), Chunk>,
+    /// The first cell opened. It and its neighbors never have mines.
+    start: Option<(i64, i64)>,
+    /// Whether unflagging a cell marks it with a question mark first.
+    question_marks: bool,
+    state: GameState,
+    /// How many safe cells have been opened.
+    score: usize,
+}
+
+impl InfiniteBoard {
+    /// Creates a board from `cfg.seed`, or a random seed if there isn't one.
+    /// It's as dense as `cfg`'s board would be, but at least `MIN_DENSITY`.
+    pub fn new(cfg: &Config) -> Self {
+        // Random seeds are kept short enough to be easy to share, like normal
+        // boards.
+        let seed = cfg.seed.unwrap_or_else(|| fastrand::u32(..).into());
+        let density = (cfg.mine_count as f64 / (cfg.cell_cols * cfg.cell_rows).max(1) as f64)
+            .clamp(MIN_DENSITY, 1.0);
+        Self {
+            seed,
+            chunk_mines: (density * CHUNK_AREA as f64).round() as usize,
+            chunks: HashMap::new(),
+            start: None,
+            question_marks: cfg.question_marks,
+            state: GameState::InProgress,
+            score: 0,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.state != GameState::InProgress
+    }
+
+    /// How many safe cells have been opened.
+    pub fn score(&self) -> usize {
+        self.score
+    }
+
+    /// Whether the first cell has been opened, which starts the clock.
+    pub fn has_started(&self) -> bool {
+        self.start.is_some()
+    }
+
+    pub fn cell(&self, x: i64, y: i64) -> Cell {
+        let (key, i) = split(x, y);
+        self.chunks
+            .get(&key)
+            .map_or(Cell::Unopened, |chunk| chunk.cells[i])
+    }
+
+    /// Whether there's a mine at x,y. Only known for chunks that have been
+    /// made, which are the ones near anything opened or flagged.
+    pub fn is_mine(&self, x: i64, y: i64) -> bool {
+        let (key, i) = split(x, y);
+        self.chunks.get(&key).is_some_and(|chunk| chunk.mines[i])
+    }
+
+    /// The number of mines around an opened cell.
+    pub fn nearby_mines(&self, x: i64, y: i64) -> u8 {
+        let (key, i) = split(x, y);
+        self.chunks
+            .get(&key)
+            .map_or(0, |chunk| chunk.mine_counts[i])
+    }
+
+    /// Opens the cell, and the ones around it if it's a 0, etc. Returns
+    /// whether anything happened.
+    pub fn open(&mut self, x: i64, y: i64) -> bool {
+        if self.is_game_over() || !self.cell(x, y).can_open() {
+            return false;
+        }
+        if self.start.is_none() {
+            self.start = Some((x, y));
+            // Chunks made for flags before the first click might have mines
+            // there.
+            for (&key, chunk) in &mut self.chunks {
+                clear_start(key, (x, y), &mut chunk.mines);
+            }
+        }
+        self.open_cells(vec![(x, y)]);
+        true
+    }
+
+    /// Opens the unflagged cells around an opened one, if it has as many flags
+    /// around it as mines. Returns whether there were the right number of flags
+    /// for that.
+    pub fn chord(&mut self, x: i64, y: i64) -> bool {
+        if self.is_game_over() || self.cell(x, y) != Cell::Opened {
+            return false;
+        }
+        let mut flags = 0;
+        let mut to_open = Vec::new();
+        do_surrounding(x, y, |sx, sy| match self.cell(sx, sy) {
+            Cell::Flagged => flags += 1,
+            cell if cell.can_open() => to_open.push((sx, sy)),
+            _ => {}
+        });
+        if flags != self.nearby_mines(x, y) {
+            return false;
+        }
+        self.open_cells(to_open);
+        true
+    }
+
+    /// Flags an unopened cell or unflags a flagged one, with a question mark in
+    /// between if they're on. Returns whether anything happened.
+    pub fn toggle_flag(&mut self, x: i64, y: i64) -> bool {
+        if self.is_game_over() {
+            return false;
+        }
+        let new_cell = match self.cell(x, y) {
+            Cell::Unopened => Cell::Flagged,
+            Cell::Opened => return false,
+            Cell::Flagged if self.question_marks => Cell::Questioned,
+            Cell::Flagged | Cell::Questioned => Cell::Unopened,
+        };
+        let (key, i) = split(x, y);
+        self.chunk_mut(key).cells[i] = new_cell;
+        true
+    }
+
+    fn open_cells(&mut self, mut cells_to_process: Vec<(i64, i64)>) {
+        while let Some((x, y)) = cells_to_process.pop() {
+            // Cells can be reached from more than one side.
+            if !self.cell(x, y).can_open() {
+                continue;
+            }
+            let (key, i) = split(x, y);
+            if self.chunk_mut(key).mines[i] {
+                self.chunk_mut(key).cells[i] = Cell::Opened;
+                self.state = GameState::Lost;
+                continue;
+            }
+            let mut mine_count = 0;
+            do_surrounding(x, y, |sx, sy| {
+                let (key, i) = split(sx, sy);
+                mine_count += u8::from(self.chunk_mut(key).mines[i]);
+            });
+            let chunk = self.chunk_mut(key);
+            chunk.cells[i] = Cell::Opened;
+            chunk.mine_counts[i] = mine_count;
+            self.score += 1;
+            if mine_count == 0 {
+                do_surrounding(x, y, |sx, sy| cells_to_process.push((sx, sy)));
+            }
+        }
+    }
+
+    /// The chunk, made now if it hasn't been yet.
+    fn chunk_mut(&mut self, key: (i64, i64)) -> &mut Chunk {
+        let (seed, chunk_mines, start) = (self.seed, self.chunk_mines, self.start);
+        self.chunks.entry(key).or_insert_with(|| {
+            let mut rng = fastrand::Rng::with_seed(chunk_seed(seed, key));
+            let mut mines = vec![false; CHUNK_AREA].into_boxed_slice();
+            for i in rng.choose_multiple(0..CHUNK_AREA, chunk_mines) {
+                mines[i] = true;
+            }
+            if let Some(start) = start {
+                clear_start(key, start, &mut mines);
+            }
+            Chunk {
+                mines,
+                cells: vec![Cell::Unopened; CHUNK_AREA].into_boxed_slice(),
+                mine_counts: vec![0; CHUNK_AREA].into_boxed_slice(),
+            }
+        })
+    }
+}
+
+/// Which chunk a cell is in, and where in it.
+fn split(x: i64, y: i64) -> ((i64, i64), usize) {
+    let key = (x.div_euclid(CHUNK_SIZE), y.div_euclid(CHUNK_SIZE));
+    let i = y.rem_euclid(CHUNK_SIZE) * CHUNK_SIZE + x.rem_euclid(CHUNK_SIZE);
+    (key, i as usize)
+}
+
+/// Mixes the board's seed with a chunk's position, so neighboring chunks
+/// don't look alike.
+fn chunk_seed(seed: u64, (chunk_x, chunk_y): (i64, i64)) -> u64 {
+    let mut hash = seed
+        ^ (chunk_x as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        ^ (chunk_y as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^ hash >> 33
+}
+
+/// Removes any mines the chunk has in or around the first cell opened.
+fn clear_start(key: (i64, i64), (start_x, start_y): (i64, i64), mines: &mut [bool]) {
+    let mut clear = |x, y| {
+        let (cell_key, i) = split(x, y);
+        if cell_key == key {
+            mines[i] = false;
+        }
+    };
+    clear(start_x, start_y);
+    do_surrounding(start_x, start_y, clear);
+}
+
+/// Calls `f` with the coords of the 8 cells around x,y, whichever chunks
+/// they're in. There's no edge to stop at.
+pub fn do_surrounding(x: i64, y: i64, mut f: impl FnMut(i64, i64)) {
+    for dy in -1..=1 {
+        for dx in -1..=1 {
+            if (dx, dy) != (0, 0) {
+                f(x + dx, y + dy);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_infinite_board() {
+    let cfg = Config {
+        seed: Some(42),
+        ..Config::default()
+    };
+    // Starting on a chunk corner, so the first opening crosses into others.
+    // The flag makes a chunk before the first click, which has to end up the
+    // same as if it hadn't.
+    let mut a = InfiniteBoard::new(&cfg);
+    let mut b = InfiniteBoard::new(&cfg);
+    assert!(a.toggle_flag(-1, -1));
+    assert!(a.open(0, 0));
+    assert!(b.open(0, 0));
+    assert_eq!(a.state(), GameState::InProgress);
+    assert_eq!(a.nearby_mines(0, 0), 0);
+    assert_eq!(a.cell(-1, -1), Cell::Flagged);
+    assert!(a.chunks.len() > 1);
+
+    let mut opened = 0;
+    let mut mine = None;
+    for (&(chunk_x, chunk_y), chunk) in &a.chunks {
+        if let Some(other) = b.chunks.get(&(chunk_x, chunk_y)) {
+            assert_eq!(chunk.mines, other.mines);
+        }
+        for i in 0..CHUNK_AREA as i64 {
+            let x = chunk_x * CHUNK_SIZE + i % CHUNK_SIZE;
+            let y = chunk_y * CHUNK_SIZE + i / CHUNK_SIZE;
+            if a.is_mine(x, y) {
+                mine = Some((x, y));
+            }
+            if a.cell(x, y) == Cell::Opened {
+                opened += 1;
+                let mut mine_count = 0;
+                do_surrounding(x, y, |sx, sy| mine_count += u8::from(a.is_mine(sx, sy)));
+                assert_eq!(a.nearby_mines(x, y), mine_count);
+            }
+        }
+    }
+    assert_eq!(opened, a.score());
+
+    let (x, y) = mine.expect("at 15% there's a mine somewhere");
+    assert!(a.open(x, y));
+    assert_eq!(a.state(), GameState::Lost);
+    assert_eq!(a.score(), opened);
+    assert!(!a.open(100, 100));
+}
@@ -0,0 +1,428 @@
+//! The window for boards with no edges (see `infinite`). It works like the
+//! normal game window, but there's nothing to win: the score is how many
+//! cells were opened before hitting a mine.
+
+use ab_glyph::{Font, FontRef};
+use minifb::{Menu, MouseButton, MouseMode, Window, WindowOptions};
+use std::time::{Duration, Instant};
+
+use crate::{bindings, board, game_window, infinite, shared};
+use bindings::{Action as BindingAction, Bindings};
+use board::{Cell, GameState};
+use game_window::{
+    CellsMouse, GameEnd, RightDrag, Status, Wheel, DIGITS_EN, DIGITS_JP, MAX_START_HEIGHT,
+    MAX_START_WIDTH, MIN_WINDOW_HEIGHT, MIN_WINDOW_WIDTH,
+};
+use infinite::InfiniteBoard;
+use shared::{Config, Lang, Stopwatch, DEFAULT_CELL_SIZE, STATUS_BAR_HEIGHT};
+
+/// Plays on a board with no edges until a mine is opened, or the window is
+/// closed.
+pub fn run(cfg: &mut Config) -> GameEnd {
+    let mut board = InfiniteBoard::new(cfg);
+    cfg.cell_size = DEFAULT_CELL_SIZE;
+    cfg.buffer_width = MAX_START_WIDTH;
+    cfg.buffer_height = MAX_START_HEIGHT;
+    // The top left cell in view. Play starts around 0,0.
+    let mut view = centered_view(cfg, (0, 0));
+
+    let bindings = Bindings::load();
+
+    let font_en = FontRef::try_from_slice(shared::FIRA_CODE_BYTES).unwrap();
+    let font_jp = FontRef::try_from_slice(shared::NOTO_SANS_JP_BYTES).unwrap();
+    let font = cfg.en_jp(&font_en, &font_jp);
+    let emoji_font = FontRef::try_from_slice(shared::NOTO_EMOJI_BYTES).unwrap();
+    let digits = cfg.en_jp(DIGITS_EN, DIGITS_JP);
+    let mut buffer = vec![0u32; cfg.buffer_width * cfg.buffer_height];
+    let mut window = Window::new(
+        "Minesweeper",
+        cfg.buffer_width,
+        cfg.buffer_height,
+        WindowOptions {
+            resize: true,
+            ..WindowOptions::default()
+        },
+    )
+    .unwrap();
+
+    const MENU_ID_NEW_GAME: usize = 1;
+    const MENU_ID_QUIT: usize = 2;
+    const MENU_ID_KEYBOARD_HELP: usize = 3;
+
+    /// The menu items with shortcuts, and the actions that bind them.
+    const MENU_ACTIONS: &[(usize, BindingAction)] = &[
+        (MENU_ID_NEW_GAME, BindingAction::NewGame),
+        (MENU_ID_QUIT, BindingAction::Quit),
+        (MENU_ID_KEYBOARD_HELP, BindingAction::KeyboardHelp),
+    ];
+
+    let add_menu_items = |menu: &mut Menu, items: &[(&str, usize)]| {
+        for &(name, id) in items {
+            let mut item = menu.add_item(name, id);
+            let shortcut = MENU_ACTIONS
+                .iter()
+                .find(|m| m.0 == id)
+                .and_then(|&(_, action)| bindings.shortcut(action));
+            if let Some((key, modifier)) = shortcut {
+                item = item.shortcut(key, modifier);
+            }
+            item.build();
+        }
+    };
+    let mut game_menu = Menu::new(cfg.en_jp("Game", "ゲーム")).unwrap();
+    add_menu_items(
+        &mut game_menu,
+        &[
+            (cfg.en_jp("New Game", "新しいゲーム"), MENU_ID_NEW_GAME),
+            (cfg.en_jp("Quit", "ゲームをやめる"), MENU_ID_QUIT),
+        ],
+    );
+    window.add_menu(&game_menu);
+    let mut help_menu = Menu::new(cfg.en_jp("Help", "ヘルプ")).unwrap();
+    add_menu_items(
+        &mut help_menu,
+        &[(
+            cfg.en_jp("Keyboard Controls", "キーボード操作"),
+            MENU_ID_KEYBOARD_HELP,
+        )],
+    );
+    window.add_menu(&help_menu);
+
+    let mut mouse = CellsMouse::new();
+    let mut right_drag = RightDrag::default();
+    let mut stopwatch = Stopwatch::default();
+    let mut showing_message_since: Option<Instant> = None;
+    let mut needs_update = true;
+    // The keyboard's cell cursor. Hidden until a key uses it.
+    let mut cursor: Option<(i64, i64)> = None;
+    let mut notice: Option<String> = None;
+    let mut is_game_over = false;
+    let mut just_lost = false;
+    let mut left_was_down = false;
+    // Whether the left click started on the restart button.
+    let mut restart_pressed = false;
+    // What the status bar was last drawn with.
+    let mut status_drawn = None;
+    while window.is_open() && !bindings.is_down(&window, BindingAction::CloseWindow) {
+        let menu_id = window
+            .is_menu_pressed()
+            .or_else(|| game_window::pressed_menu_shortcut(&window, &bindings, MENU_ACTIONS));
+        match menu_id {
+            Some(MENU_ID_NEW_GAME) => return GameEnd::Restart,
+            Some(MENU_ID_QUIT) => break,
+            Some(MENU_ID_KEYBOARD_HELP) => {
+                notice = Some(game_window::controls_help(
+                    cfg,
+                    &bindings,
+                    &[
+                        BindingAction::Open,
+                        BindingAction::Flag,
+                        BindingAction::Chord,
+                        BindingAction::CursorUp,
+                        BindingAction::CursorDown,
+                        BindingAction::CursorLeft,
+                        BindingAction::CursorRight,
+                        BindingAction::ScrollUp,
+                        BindingAction::ScrollDown,
+                        BindingAction::ScrollLeft,
+                        BindingAction::ScrollRight,
+                        BindingAction::NewGame,
+                        BindingAction::KeyboardHelp,
+                    ],
+                ));
+                needs_update = true;
+            }
+            _ => {}
+        }
+
+        // There's always more board, so the window only changes how much of
+        // it is in view.
+        let (width, height) = window.get_size();
+        let size = (width.max(MIN_WINDOW_WIDTH), height.max(MIN_WINDOW_HEIGHT));
+        if size != (cfg.buffer_width, cfg.buffer_height) {
+            (cfg.buffer_width, cfg.buffer_height) = size;
+            buffer.resize(size.0 * size.1, 0);
+            needs_update = true;
+        }
+        let mut scroll = (0, 0);
+        match game_window::read_wheel(&window) {
+            Some(Wheel::Zoom(grow)) => {
+                cfg.cell_size = game_window::zoomed(cfg.cell_size, grow);
+                needs_update = true;
+            }
+            Some(Wheel::Scroll(dx, dy)) => scroll = (dx, dy),
+            None => {}
+        }
+        if let Some(delta) = game_window::scroll_keys(&window, &bindings, view_size(cfg)) {
+            scroll = delta;
+        }
+        if scroll != (0, 0) {
+            view = (view.0 + scroll.0 as i64, view.1 + scroll.1 as i64);
+            needs_update = true;
+        }
+        if let Some((start, (dx, dy))) = right_drag.update(&window, cfg.cell_size, view, true) {
+            mouse.right.cancel();
+            let new_view = (start.0 + dx as i64, start.1 + dy as i64);
+            needs_update |= new_view != view;
+            view = new_view;
+        }
+
+        let left_down = window.get_mouse_down(MouseButton::Left);
+        let on_restart = window
+            .get_mouse_pos(MouseMode::Discard)
+            .is_some_and(|pos| game_window::is_on_restart_button(cfg, pos));
+        if left_down && !left_was_down {
+            restart_pressed = on_restart;
+        }
+        if restart_pressed && !left_down && on_restart {
+            board = InfiniteBoard::new(cfg);
+            view = centered_view(cfg, (0, 0));
+            stopwatch = Stopwatch::default();
+            cursor = None;
+            is_game_over = false;
+            showing_message_since = None;
+            needs_update = true;
+        }
+        if !left_down {
+            restart_pressed = false;
+        }
+        left_was_down = left_down;
+
+        let mut key_action = game_window::keyboard_action(&window, &bindings);
+        if let Some(action) = key_action {
+            let (dx, dy) = match action {
+                BindingAction::CursorUp => (0, -1),
+                BindingAction::CursorDown => (0, 1),
+                BindingAction::CursorLeft => (-1, 0),
+                BindingAction::CursorRight => (1, 0),
+                _ => (0, 0),
+            };
+            let (view_cols, view_rows) = view_size(cfg);
+            let (x, y) = match cursor {
+                // The first key only shows the cursor, so nothing happens out of sight.
+                None => {
+                    key_action = None;
+                    (view.0 + view_cols as i64 / 2, view.1 + view_rows as i64 / 2)
+                }
+                Some((x, y)) => (x + dx, y + dy),
+            };
+            cursor = Some((x, y));
+            // The view follows the cursor.
+            let (cols, rows) = whole_cells_in_view(cfg);
+            view = (
+                view.0.min(x).max(x + 1 - cols),
+                view.1.min(y).max(y + 1 - rows),
+            );
+            needs_update = true;
+        }
+
+        let accept_input = !is_game_over
+            || showing_message_since
+                .is_some_and(|d| Instant::now() - d > Duration::from_secs_f32(1.0));
+        if accept_input {
+            let mods = bindings::mods_down(&window);
+            let click = mouse
+                .click(&window, |pos| pos_to_cell(cfg, view, pos))
+                .and_then(|(trigger, cell)| Some((bindings.mouse_action(trigger, mods)?, cell)))
+                .or(key_action.zip(cursor));
+            if showing_message_since.is_some()
+                && click.is_some_and(|(action, _)| action == BindingAction::Open)
+            {
+                showing_message_since = None;
+                needs_update = true;
+            } else {
+                let changed = match click {
+                    Some((BindingAction::Chord, (x, y))) => {
+                        let changed = board.chord(x, y);
+                        if !changed && board.cell(x, y) == Cell::Opened {
+                            game_window::play_bell();
+                        }
+                        changed
+                    }
+                    Some((BindingAction::Open, (x, y))) => board.open(x, y),
+                    Some((BindingAction::Flag, (x, y))) => board.toggle_flag(x, y),
+                    _ => false,
+                };
+                needs_update |= changed;
+            }
+        }
+
+        if !is_game_over && board.is_game_over() {
+            is_game_over = true;
+            just_lost = true;
+        }
+        if board.has_started() && !is_game_over {
+            stopwatch.start();
+        } else {
+            stopwatch.stop();
+        }
+
+        let restart_held = restart_pressed && on_restart;
+        let status = Status {
+            counter: board.score() as isize,
+            seconds: stopwatch.elapsed().as_secs(),
+            face: match board.state() {
+                _ if restart_held => '🙂',
+                GameState::Lost => '😵',
+                _ if mouse.left.held.is_some() => '😮',
+                _ => '🙂',
+            },
+            face_pressed: restart_held,
+        };
+
+        if needs_update {
+            let (view_cols, view_rows) = view_size(cfg);
+            // One lookup per cell, rather than per pixel.
+            let colors: Vec<u32> = (0..view_rows as i64)
+                .flat_map(|y| (0..view_cols as i64).map(move |x| (x, y)))
+                .map(|(x, y)| match board.cell(view.0 + x, view.1 + y) {
+                    Cell::Opened => cfg.theme.opened,
+                    _ => cfg.theme.unopened,
+                })
+                .collect();
+            let cell_pixels = cfg.cell_size + 1;
+            for (i, px) in buffer.iter_mut().enumerate() {
+                let row = i / cfg.buffer_width;
+                let col = i % cfg.buffer_width;
+                *px = if row < STATUS_BAR_HEIGHT {
+                    cfg.theme.status_bar
+                } else if (row - STATUS_BAR_HEIGHT).is_multiple_of(cell_pixels)
+                    || col.is_multiple_of(cell_pixels)
+                {
+                    cfg.theme.line
+                } else {
+                    colors[(row - STATUS_BAR_HEIGHT) / cell_pixels * view_cols + col / cell_pixels]
+                };
+            }
+
+            for y in view.1..view.1 + view_rows as i64 {
+                for x in view.0..view.0 + view_cols as i64 {
+                    let is_mine = board.is_mine(x, y);
+                    let (font, c, color) = match board.cell(x, y) {
+                        Cell::Opened if is_mine => (&emoji_font, '💣', cfg.theme.text_light),
+                        Cell::Opened => match board.nearby_mines(x, y) {
+                            0 => continue,
+                            n => (font, digits[usize::from(n)], cfg.number_color(n)),
+                        },
+                        Cell::Flagged if is_game_over && !is_mine => {
+                            (&emoji_font, '🚩', cfg.theme.text_wrong_flag)
+                        }
+                        Cell::Flagged => (&emoji_font, '🚩', cfg.theme.text_dark),
+                        _ if is_game_over && is_mine => (&emoji_font, '💣', cfg.theme.text_dark),
+                        Cell::Questioned => (font, '?', cfg.theme.text_dark),
+                        Cell::Unopened => continue,
+                    };
+                    if let Some((left, top)) = cell_to_pos(cfg, view, (x, y)) {
+                        game_window::draw_char_centered(
+                            cfg,
+                            font,
+                            c,
+                            color,
+                            (left + 1, top + 1, cfg.cell_size),
+                            &mut buffer,
+                        );
+                    }
+                }
+            }
+            if let Some((left, top)) = cursor.and_then(|cell| cell_to_pos(cfg, view, cell)) {
+                game_window::outline_square(
+                    cfg,
+                    cfg.theme.cursor,
+                    (left + 1, top + 1),
+                    &mut buffer,
+                );
+            }
+
+            game_window::draw_status_bar(cfg, &font_en, &emoji_font, status, &mut buffer);
+            status_drawn = Some(status);
+
+            let message = if just_lost {
+                just_lost = false;
+                Some(match cfg.lang {
+                    Lang::En => format!("Boom!\nCells cleared: {}", board.score()),
+                    Lang::Jp => format!("地雷を踏みました。\n開いたマス：{}", board.score()),
+                })
+            } else {
+                notice.take()
+            };
+            if let Some(msg) = message {
+                game_window::show_message(
+                    cfg,
+                    &msg,
+                    font.as_scaled(game_window::message_font_size(cfg)),
+                    &mut buffer,
+                );
+                showing_message_since = Some(Instant::now());
+            }
+
+            window.set_title(&format!(
+                "{} - {}{}",
+                cfg.en_jp("Minesweeper - No Edges", "マインスイーパ - 無限"),
+                cfg.en_jp("Seed: ", "シード："),
+                board.seed()
+            ));
+
+            needs_update = false;
+        } else if status_drawn != Some(status) && showing_message_since.is_none() {
+            // Just the timer ticking. (Skipped under a message so it doesn't
+            // get drawn over.)
+            game_window::draw_status_bar(cfg, &font_en, &emoji_font, status, &mut buffer);
+            status_drawn = Some(status);
+        }
+
+        window
+            .update_with_buffer(&buffer, cfg.buffer_width, cfg.buffer_height)
+            .unwrap();
+    }
+
+    GameEnd::Quit
+}
+
+/// How many columns and rows are in view, counting ones cut off at the edge
+/// of the window.
+fn view_size(cfg: &Config) -> (usize, usize) {
+    let cell_pixels = cfg.cell_size + 1;
+    (
+        (cfg.buffer_width - 1).div_ceil(cell_pixels),
+        (cfg.buffer_height - STATUS_BAR_HEIGHT - 1).div_ceil(cell_pixels),
+    )
+}
+
+/// How many whole columns and rows fit in the window.
+fn whole_cells_in_view(cfg: &Config) -> (i64, i64) {
+    let cell_pixels = cfg.cell_size + 1;
+    (
+        ((cfg.buffer_width - 1) / cell_pixels).max(1) as i64,
+        ((cfg.buffer_height - STATUS_BAR_HEIGHT - 1) / cell_pixels).max(1) as i64,
+    )
+}
+
+/// The top left cell of a view with `(x, y)` in the middle.
+fn centered_view(cfg: &Config, (x, y): (i64, i64)) -> (i64, i64) {
+    let (cols, rows) = whole_cells_in_view(cfg);
+    (x - cols / 2, y - rows / 2)
+}
+
+/// Converts pixel coords to cell coords.
+fn pos_to_cell(cfg: &Config, view: (i64, i64), (x, y): (f32, f32)) -> Option<(i64, i64)> {
+    // Truncate the floats
+    let (x, y) = (x as usize, (y as usize).checked_sub(STATUS_BAR_HEIGHT)?);
+    let cell_pixels = cfg.cell_size + 1;
+    if x.is_multiple_of(cell_pixels) || y.is_multiple_of(cell_pixels) {
+        return None;
+    }
+    Some((
+        view.0 + (x / cell_pixels) as i64,
+        view.1 + (y / cell_pixels) as i64,
+    ))
+}
+
+/// Converts cell coords to the pixel coords of its top left corner (on the
+/// grid lines), or `None` if it's off the top or left of the view.
+fn cell_to_pos(cfg: &Config, view: (i64, i64), (x, y): (i64, i64)) -> Option<(usize, usize)> {
+    let cell_pixels = cfg.cell_size + 1;
+    Some((
+        usize::try_from(x - view.0).ok()? * cell_pixels,
+        STATUS_BAR_HEIGHT + usize::try_from(y - view.1).ok()? * cell_pixels,
+    ))
+}
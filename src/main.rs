@@ -1,7 +1,23 @@
+mod bindings;
+mod board;
+mod board_code;
+mod board_file;
 mod game_window;
+mod infinite;
+mod infinite_window;
+mod metrics;
+mod probability;
+mod rawvf;
+mod records;
+mod replay;
+mod save;
 mod setup_window;
 mod shared;
+mod solver;
+mod stats;
 mod text;
+mod theme;
+mod tui;
 
 use game_window::GameEnd;
 use shared::Config;
@@ -13,18 +29,42 @@ fn main() {
         .map(|s| s.as_str())
         .unwrap_or("minesweeper.exe");
     let mut help_arg = false;
+    let mut no_guess_arg = false;
+    let mut infinite_arg = false;
+    let mut tui_arg = false;
     let mut rows_arg: Option<&str> = None;
     let mut cols_arg: Option<&str> = None;
     let mut mines_arg: Option<&str> = None;
+    let mut seed_arg: Option<&str> = None;
+    let mut code_arg: Option<&str> = None;
+    let mut board_arg: Option<&str> = None;
+    let mut replay_arg: Option<&str> = None;
+    let mut speed_arg: Option<&str> = None;
     for arg in args.iter().skip(1) {
         if matches!(arg.as_str(), "help" | "-h" | "-help" | "--help") {
             help_arg = true;
+        } else if arg == "no-guess" {
+            no_guess_arg = true;
+        } else if arg == "infinite" {
+            infinite_arg = true;
+        } else if arg == "tui" {
+            tui_arg = true;
         } else if arg.starts_with("rows=") {
             rows_arg = Some(arg);
         } else if arg.starts_with("cols=") {
             cols_arg = Some(arg);
         } else if arg.starts_with("mines=") {
             mines_arg = Some(arg);
+        } else if arg.starts_with("seed=") {
+            seed_arg = Some(arg);
+        } else if let Some(code) = arg.strip_prefix("code=") {
+            code_arg = Some(code);
+        } else if let Some(path) = arg.strip_prefix("board=") {
+            board_arg = Some(path);
+        } else if let Some(path) = arg.strip_prefix("replay=") {
+            replay_arg = Some(path);
+        } else if let Some(speed) = arg.strip_prefix("speed=") {
+            speed_arg = Some(speed);
         } else {
             eprint!("Unknown flag '{arg}'. ");
             print_help(app_name);
@@ -37,7 +77,7 @@ fn main() {
         return;
     }
 
-    fn parse_num_arg(arg: Option<&str>) -> Option<usize> {
+    fn parse_num_arg<T: std::str::FromStr>(arg: Option<&str>) -> Option<T> {
         let arg = arg?;
         let equals_sign_idx = arg.find('=').expect("already checked to exist");
         let num = &arg[equals_sign_idx + 1..];
@@ -48,7 +88,10 @@ fn main() {
         Some(num)
     }
 
-    let mut cfg = Config::default();
+    let mut cfg = Config {
+        theme: theme::Theme::load(),
+        ..Config::default()
+    };
     if let Some(rows) = parse_num_arg(rows_arg) {
         cfg.cell_rows = rows;
     }
@@ -58,13 +101,90 @@ fn main() {
     if let Some(mines) = parse_num_arg(mines_arg) {
         cfg.mine_count = mines;
     }
+    cfg.no_guess = no_guess_arg;
+    cfg.infinite = infinite_arg;
+    cfg.seed = parse_num_arg(seed_arg);
+    cfg.code = code_arg.map(str::to_owned);
+    if let Some(path) = board_arg {
+        match board_file::read(std::path::Path::new(path)) {
+            Ok(board) => cfg.board = Some(board),
+            Err(err) => {
+                eprintln!("Couldn't read the board: {err}");
+                return;
+            }
+        }
+    }
+    // Setup only keeps things in range once they're changed there, and some
+    // games skip it.
+    if let Err(err) = cfg.validate() {
+        eprintln!("Can't play that board: {err}");
+        return;
+    }
+    if tui_arg {
+        if cfg.infinite {
+            eprintln!("Boards with no edges can't be played in the terminal.");
+            return;
+        }
+        if let Err(err) = tui::run(&cfg) {
+            eprintln!("Couldn't play in the terminal: {err}");
+        }
+        return;
+    }
+    let speed = match speed_arg {
+        None | Some("1") => replay::Speed::Normal,
+        Some("2") => replay::Speed::Double,
+        Some("instant") => replay::Speed::Instant,
+        Some(speed) => {
+            eprintln!("The speed can be 1, 2 or instant, not '{speed}'.");
+            return;
+        }
+    };
+    let mut playback = match replay_arg {
+        Some(path) => match replay::read(std::path::Path::new(path)) {
+            Ok(replay) => {
+                let replay_cfg = Config {
+                    code: Some(replay.code.clone()),
+                    ..Config::default()
+                };
+                if let Err(err) = replay_cfg.validate() {
+                    eprintln!("Can't play that replay's board: {err}");
+                    return;
+                }
+                Some(replay::Playback::new(replay, speed))
+            }
+            Err(err) => {
+                eprintln!("Couldn't read the replay: {err}");
+                return;
+            }
+        },
+        None => None,
+    };
+    let mut saved_game = None;
     loop {
-        match setup_window::run(cfg) {
-            Some(new_cfg) => cfg = new_cfg,
-            None => return,
+        // Loading a game from the game window, watching a replay, or playing
+        // a board file skips setup.
+        if saved_game.is_none() && playback.is_none() && cfg.board.is_none() {
+            match setup_window::run(cfg) {
+                Some((new_cfg, resumed_game)) => {
+                    cfg = new_cfg;
+                    saved_game = resumed_game;
+                }
+                None => return,
+            }
         }
-        match game_window::run(&mut cfg) {
+        // Saved games, replays and board files all have edges.
+        let end =
+            if cfg.infinite && saved_game.is_none() && playback.is_none() && cfg.board.is_none() {
+                infinite_window::run(&mut cfg)
+            } else {
+                game_window::run(&mut cfg, saved_game.take(), playback.take())
+            };
+        // The board file is only for the first game; the restart button
+        // replays it, but a new game goes through setup.
+        cfg.board = None;
+        match end {
             GameEnd::Restart => {}
+            GameEnd::Load(game) => saved_game = Some(*game),
             GameEnd::Quit => return,
         }
     }
@@ -73,7 +193,7 @@ fn main() {
 fn print_help(app_name: &str) {
     let default_cfg = Config::default();
     eprintln!(
-        "USAGE: {app_name} [rows={}] [cols={}] [mines={}]",
+        "USAGE: {app_name} [rows={}] [cols={}] [mines={}] [seed=<number>] [code=<board code>] [board=<file>] [no-guess] [infinite] [tui] [replay=<file> [speed=1|2|instant]]",
         default_cfg.cell_rows, default_cfg.cell_cols, default_cfg.mine_count
     );
 }
@@ -0,0 +1,106 @@
+//! How hard a board is, and how well it was played.
+
+use std::time::Duration;
+
+use crate::board::{self, Board};
+
+/// A game's numbers, enough to work out everything else given its time.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Metrics {
+    /// The fewest clicks that clear the board, without flags or chords.
+    pub bbbv: usize,
+    pub clicks: usize,
+    /// Clicks that changed something.
+    pub effective_clicks: usize,
+}
+
+impl Metrics {
+    /// The board's mines need to be placed already.
+    pub fn of(board: &Board) -> Self {
+        let clicks = board.clicks();
+        Self {
+            bbbv: bbbv(board),
+            clicks: clicks.total,
+            effective_clicks: clicks.effective,
+        }
+    }
+
+    pub fn bbbv_per_sec(&self, time: Duration) -> f64 {
+        self.bbbv as f64 / time.as_secs_f64().max(0.001)
+    }
+
+    /// 3BV per effective click, as a percentage. Can go over 100 with chords.
+    pub fn efficiency(&self) -> f64 {
+        self.bbbv as f64 / self.effective_clicks.max(1) as f64 * 100.0
+    }
+
+    /// Index of efficiency: 3BV per click, wasted ones included.
+    pub fn ioe(&self) -> f64 {
+        self.bbbv as f64 / self.clicks.max(1) as f64
+    }
+
+    /// Time divided by 3BV/s. Lower is better.
+    pub fn rqp(&self, time: Duration) -> f64 {
+        time.as_secs_f64() / self.bbbv_per_sec(time)
+    }
+}
+
+/// Counts the board's 3BV: each opening (a patch of zeros and the numbers
+/// around it) takes one click, and every other safe cell takes one more.
+pub fn bbbv(board: &Board) -> usize {
+    let (cols, rows) = (board.cols(), board.rows());
+    let mut counted = vec![false; cols * rows];
+    let mut bbbv = 0;
+
+    let is_zero = |x, y| !board.is_mine(x, y) && board.nearby_mines(x, y) == 0;
+    for y in 0..rows {
+        for x in 0..cols {
+            if counted[y * cols + x] || !is_zero(x, y) {
+                continue;
+            }
+            bbbv += 1;
+            counted[y * cols + x] = true;
+            let mut to_visit = vec![(x, y)];
+            while let Some((x, y)) = to_visit.pop() {
+                board::do_surrounding(cols, rows, x, y, |sx, sy| {
+                    if !counted[sy * cols + sx] {
+                        counted[sy * cols + sx] = true;
+                        if is_zero(sx, sy) {
+                            to_visit.push((sx, sy));
+                        }
+                    }
+                });
+            }
+        }
+    }
+
+    let safe_cells_left = (0..rows)
+        .flat_map(|y| (0..cols).map(move |x| (x, y)))
+        .filter(|&(x, y)| !counted[y * cols + x] && !board.is_mine(x, y))
+        .count();
+    bbbv + safe_cells_left
+}
+
+#[test]
+fn test_bbbv() {
+    // . . 1 1
+    // . . 1 *
+    // 1 1 1 1
+    // * 1 . .
+    // Two openings, plus the 1 in the top right that isn't next to either.
+    let mut mines = vec![false; 16].into_boxed_slice();
+    mines[4 + 3] = true;
+    mines[3 * 4] = true;
+    let board = Board::with_mines(4, 4, mines);
+    assert_eq!(bbbv(&board), 3);
+
+    let metrics = Metrics {
+        bbbv: 4,
+        clicks: 8,
+        effective_clicks: 5,
+    };
+    assert_eq!(metrics.ioe(), 0.5);
+    assert_eq!(metrics.efficiency(), 80.0);
+    assert_eq!(metrics.bbbv_per_sec(Duration::from_secs(2)), 2.0);
+    assert_eq!(metrics.rqp(Duration::from_secs(2)), 1.0);
+}
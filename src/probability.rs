@@ -0,0 +1,273 @@
+//! Works out the exact chance of each unopened cell being a mine, from what
+//! the player can see.
+
+use crate::board::Board;
+use crate::solver::{self, Component, Solver};
+
+/// Picks a cell for the player to open: a provably safe one if there are
+/// any, otherwise the one least likely to be a mine.
+pub fn hint(board: &Board) -> Option<(usize, usize)> {
+    if board.is_game_over() {
+        return None;
+    }
+    if !board.mines_placed() {
+        // The first click is always safe, and the middle tends to open up the
+        // most. Fixed layouts have their mines already, so they don't get this.
+        return Some((board.cols() / 2, board.rows() / 2));
+    }
+    if let Some(d) = solver::solve(board).into_iter().find(|d| !d.is_mine) {
+        return Some((d.x, d.y));
+    }
+    mine_probabilities(board)
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, p)| Some((i, p?)))
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(i, _)| (i % board.cols(), i / board.cols()))
+}
+
+/// Returns each cell's chance of being a mine, in row-major order. Opened and
+/// flagged cells get `None`, as do cells that don't fit the numbers at all
+/// (because of a wrong flag).
+///
+/// Frontier components too tangled to enumerate are approximated as if their
+/// numbers weren't there.
+pub fn mine_probabilities(board: &Board) -> Vec<Option<f64>> {
+    let cell_count = board.cols() * board.rows();
+    let mut probabilities = vec![None; cell_count];
+
+    // The cheap rules shrink the frontier that has to be enumerated.
+    let mut solver = Solver::new(board);
+    while solver.apply_single() || solver.apply_subset() {}
+    for d in &solver.deductions {
+        probabilities[d.y * board.cols() + d.x] = Some(if d.is_mine { 1.0 } else { 0.0 });
+    }
+    let Some(enumeration) = solver.enumerate() else {
+        return probabilities;
+    };
+
+    let components: Vec<Weights> = enumeration.components.iter().map(Weights::new).collect();
+    let interior_len = enumeration.interior.len();
+    let max_frontier = components.iter().map(|c| c.totals.len() - 1).sum::<usize>();
+    // `interior_ways[t]` is the relative number of ways the interior can hold
+    // the rest of the mines when the frontier has `t` of them.
+    let interior_ways: Vec<f64> = match enumeration.mines_left {
+        Some(mines_left) => {
+            let mut ln_ways = vec![f64::NEG_INFINITY; max_frontier + 1];
+            // Work down from the most mines the interior can hold, one
+            // binomial coefficient from the last.
+            let first = mines_left.saturating_sub(interior_len);
+            if first <= max_frontier.min(mines_left) {
+                ln_ways[first] = ln_choose(interior_len, mines_left - first);
+                for t in first + 1..=max_frontier.min(mines_left) {
+                    let rest = (mines_left - t + 1) as f64;
+                    ln_ways[t] = ln_ways[t - 1] + (rest / (interior_len as f64 - rest + 1.0)).ln();
+                }
+            }
+            normalized_exp(&ln_ways)
+        }
+        // Too many flags to say anything from the mine count.
+        None => vec![1.0; max_frontier + 1],
+    };
+
+    // How the other components' totals are distributed, for each component.
+    let mut prefixes = vec![vec![1.0]];
+    for c in &components {
+        prefixes.push(multiply(prefixes.last().unwrap(), &c.totals));
+    }
+    let mut suffixes = vec![vec![1.0]];
+    for c in components.iter().rev() {
+        suffixes.push(multiply(suffixes.last().unwrap(), &c.totals));
+    }
+    suffixes.reverse();
+
+    for (ci, (component, weights)) in enumeration.components.iter().zip(&components).enumerate() {
+        let others = multiply(&prefixes[ci], &suffixes[ci + 1]);
+        // `factor[k]` is the weight of this component having `k` mines, per solution.
+        let factor: Vec<f64> = (0..weights.totals.len())
+            .map(|k| {
+                others
+                    .iter()
+                    .enumerate()
+                    .map(|(t, &w)| w * interior_ways[k + t])
+                    .sum()
+            })
+            .collect();
+        let total: f64 = weights.totals.iter().zip(&factor).map(|(w, f)| w * f).sum();
+        if total <= 0.0 {
+            continue;
+        }
+        for (j, &i) in component.cells.iter().enumerate() {
+            let mines: f64 = (0..weights.totals.len())
+                .map(|k| weights.mines[k][j] * factor[k])
+                .sum();
+            probabilities[i] = Some(mines / total);
+        }
+    }
+
+    if interior_len > 0 {
+        if let Some(mines_left) = enumeration.mines_left {
+            let all = prefixes.last().unwrap();
+            let mut total = 0.0;
+            let mut expected_mines = 0.0;
+            for (t, (&w, &ways)) in all.iter().zip(&interior_ways).enumerate() {
+                if let Some(rest) = mines_left.checked_sub(t) {
+                    total += w * ways;
+                    expected_mines += w * ways * rest as f64;
+                }
+            }
+            if total > 0.0 {
+                let p = expected_mines / total / interior_len as f64;
+                for &i in &enumeration.interior {
+                    probabilities[i] = Some(p);
+                }
+            }
+        }
+    }
+
+    // Only unopened cells get a chance.
+    for (i, p) in probabilities.iter_mut().enumerate() {
+        if !board.cell(i % board.cols(), i / board.cols()).can_open() {
+            *p = None;
+        }
+    }
+    probabilities
+}
+
+/// A component's solution counts, scaled down so they can't overflow. Only
+/// their ratios matter.
+struct Weights {
+    /// Relative number of solutions with `k` mines.
+    totals: Vec<f64>,
+    /// Relative number of those with a mine on each cell.
+    mines: Vec<Vec<f64>>,
+}
+
+impl Weights {
+    fn new(component: &Component) -> Self {
+        let n = component.cells.len();
+        let (totals, mines) = if component.complete {
+            (
+                component.solutions.clone(),
+                component.mine_solutions.clone(),
+            )
+        } else {
+            // Pretend the numbers aren't there: every cell is equally likely.
+            let totals = normalized_exp(&(0..=n).map(|k| ln_choose(n, k)).collect::<Vec<_>>());
+            let mines = totals
+                .iter()
+                .enumerate()
+                .map(|(k, &w)| vec![w * k as f64 / n as f64; n])
+                .collect();
+            (totals, mines)
+        };
+        let max = totals.iter().copied().fold(0.0, f64::max);
+        Self {
+            totals: totals.iter().map(|w| w / max).collect(),
+            mines: mines
+                .into_iter()
+                .map(|row| row.into_iter().map(|w| w / max).collect())
+                .collect(),
+        }
+    }
+}
+
+/// Multiplies two polynomials (i.e., combines two independent distributions
+/// of mine totals), normalized so the biggest coefficient is 1.
+fn multiply(a: &[f64], b: &[f64]) -> Vec<f64> {
+    let mut product = vec![0.0; a.len() + b.len() - 1];
+    for (i, &x) in a.iter().enumerate() {
+        for (j, &y) in b.iter().enumerate() {
+            product[i + j] += x * y;
+        }
+    }
+    let max = product.iter().copied().fold(0.0, f64::max);
+    if max > 0.0 {
+        product.iter_mut().for_each(|w| *w /= max);
+    }
+    product
+}
+
+/// Exponentiates logs, scaled so the biggest result is 1.
+fn normalized_exp(lns: &[f64]) -> Vec<f64> {
+    let max = lns.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    if max == f64::NEG_INFINITY {
+        return vec![0.0; lns.len()];
+    }
+    lns.iter().map(|&ln| (ln - max).exp()).collect()
+}
+
+/// The natural log of n choose k.
+fn ln_choose(n: usize, k: usize) -> f64 {
+    if k > n {
+        return f64::NEG_INFINITY;
+    }
+    let k = k.min(n - k);
+    (0..k).map(|i| ((n - i) as f64 / (k - i) as f64).ln()).sum()
+}
+#[test]
+fn test_ln_choose() {
+    assert!((ln_choose(5, 2).exp() - 10.0).abs() < 1e-9);
+    assert!((ln_choose(52, 5).exp() - 2598960.0).abs() < 1e-3);
+    assert_eq!(ln_choose(3, 4), f64::NEG_INFINITY);
+}
+
+#[test]
+fn test_mine_probabilities() {
+    // ? ?
+    // 1 ?   One mine, equally likely to be in any of the three.
+    let mines = vec![true, false, false, false].into_boxed_slice();
+    let mut board = Board::with_mines(2, 2, mines);
+    board.open(0, 1);
+    let p = mine_probabilities(&board);
+    for i in [0, 1, 3] {
+        assert!((p[i].unwrap() - 1.0 / 3.0).abs() < 1e-9);
+    }
+    assert_eq!(p[2], None);
+}
+
+#[test]
+fn test_hint() {
+    // ? ? ?
+    // 1 2 1   Only the middle is safe.
+    let mines = vec![true, false, true, false, false, false].into_boxed_slice();
+    let mut board = Board::with_mines(3, 2, mines);
+    board.open(0, 1);
+    board.open(1, 1);
+    board.open(2, 1);
+    assert_eq!(hint(&board), Some((1, 0)));
+}
+
+#[test]
+fn test_hint_before_the_first_click() {
+    let board = Board::new(&crate::shared::Config::default());
+    assert_eq!(hint(&board), Some((5, 5)));
+
+    // A fixed layout's first click isn't safe, so the middle is a guess like
+    // any other cell.
+    let mut mines = vec![false; 9].into_boxed_slice();
+    mines[4] = true;
+    let board = Board::with_mines(3, 3, mines);
+    assert_ne!(hint(&board), Some((1, 1)));
+}
+
+#[test]
+fn test_interior_probabilities_add_up() {
+    let mut rng = fastrand::Rng::with_seed(3);
+    for _ in 0..50 {
+        let mines: Box<[bool]> = (0..64).map(|_| rng.u8(0..6) == 0).collect();
+        let mine_count = mines.iter().filter(|&&b| b).count();
+        let mut board = Board::with_mines(8, 8, mines);
+        if board.is_mine(0, 0) {
+            continue;
+        }
+        board.open(0, 0);
+        let p = mine_probabilities(&board);
+        // The expected number of mines is the actual number of mines.
+        let expected: f64 = p.iter().flatten().sum();
+        assert!(
+            (expected - mine_count as f64).abs() < 1e-6,
+            "{expected} != {mine_count}"
+        );
+    }
+}
@@ -0,0 +1,288 @@
+//! The RAW minesweeper video format (RAWVF), which other programs use to share
+//! games.
+//!
+//! A file has `Key: Value` header lines, the mines under `Board:` (`*` for a
+//! mine, anything else for a safe cell), then mouse events under `Events:`,
+//! as `time type x y (col row)`. Pixel positions are for 16 pixel cells, and
+//! columns and rows count from 1. Presses and releases turn into clicks the
+//! way classic Minesweeper does it: right presses flag, left releases open,
+//! and releasing either button while both are down chords.
+
+use std::{io, time::Duration};
+
+use crate::board::Board;
+use crate::board_code;
+use crate::records;
+use crate::replay::{Action, Input, Replay};
+use crate::shared::{MAX_COLS, MAX_ROWS, MIN_COLS, MIN_ROWS};
+
+const SQUARE_SIZE: usize = 16;
+
+/// Writes a replay as RAWVF. Fails if it has undos, which the format can't
+/// show.
+pub fn export(replay: &Replay) -> Result<String, &'static str> {
+    if replay
+        .inputs
+        .iter()
+        .any(|i| matches!(i.action, Action::Undo | Action::Redo))
+    {
+        return Err("RAWVF can't show undos");
+    }
+    let board = replay.board();
+    let mut s = format!(
+        "RawVF_Version: Rev7\n\
+         Program: Minesweeper\n\
+         Player: {}\n\
+         Width: {}\n\
+         Height: {}\n\
+         Mines: {}\n\
+         Marks: {}\n\
+         Mode: Classic\n\
+         Board:\n",
+        records::player_name(),
+        board.cols(),
+        board.rows(),
+        board.mine_count(),
+        if replay.question_marks { "On" } else { "Off" },
+    );
+    for y in 0..board.rows() {
+        for x in 0..board.cols() {
+            s.push(if board.is_mine(x, y) { '*' } else { '0' });
+        }
+        s.push('\n');
+    }
+    s += "Events:\n";
+    for input in &replay.inputs {
+        let (press, release, x, y) = match input.action {
+            Action::Open(x, y) => ("lc", "lr", x, y),
+            Action::Flag(x, y) => ("rc", "rr", x, y),
+            Action::Chord(x, y) => ("mc", "mr", x, y),
+            Action::Undo | Action::Redo => unreachable!("checked above"),
+        };
+        let secs = input.time.as_secs_f64();
+        let (px, py) = (
+            x * SQUARE_SIZE + SQUARE_SIZE / 2,
+            y * SQUARE_SIZE + SQUARE_SIZE / 2,
+        );
+        for kind in [press, release] {
+            s += &format!("{secs:.3} {kind} {px} {py} ({} {})\n", x + 1, y + 1);
+        }
+    }
+    Ok(s)
+}
+
+/// Reads a RAWVF file as a replay.
+pub fn import(s: &str) -> io::Result<Replay> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+    let mut lines = s.lines().map(str::trim);
+
+    let (mut cols, mut rows, mut mine_count) = (None, None, None);
+    let mut question_marks = false;
+    for line in lines.by_ref() {
+        if line == "Board:" {
+            break;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let number = || {
+            value
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| invalid(format!("bad number in '{line}'")))
+        };
+        match key {
+            "Width" => cols = Some(number()?),
+            "Height" => rows = Some(number()?),
+            "Mines" => mine_count = Some(number()?),
+            "Marks" => question_marks = value.trim().eq_ignore_ascii_case("on"),
+            // Everything else is just for people.
+            _ => {}
+        }
+    }
+    let (Some(cols), Some(rows)) = (cols, rows) else {
+        return Err(invalid("the board size is missing".to_owned()));
+    };
+    // Checked before making room for the mines, since the header could say
+    // anything.
+    if !(MIN_COLS..=MAX_COLS).contains(&cols) || !(MIN_ROWS..=MAX_ROWS).contains(&rows) {
+        return Err(invalid(format!("can't have a {cols}x{rows} board")));
+    }
+
+    let mut mines = Vec::with_capacity(cols * rows);
+    for line in lines.by_ref().take(rows) {
+        if line.chars().count() != cols {
+            return Err(invalid(format!("board row '{line}' is the wrong length")));
+        }
+        mines.extend(line.chars().map(|c| c == '*'));
+    }
+    if mines.len() != cols * rows {
+        return Err(invalid("not enough board rows".to_owned()));
+    }
+    if mine_count.is_some_and(|count| count != mines.iter().filter(|&&m| m).count()) {
+        return Err(invalid("the mine count doesn't match the board".to_owned()));
+    }
+    let board = Board::with_mines(cols, rows, mines.into_boxed_slice());
+
+    if !lines.by_ref().any(|line| line == "Events:") {
+        return Err(invalid("there aren't any events".to_owned()));
+    }
+    let mut inputs = Vec::new();
+    let (mut left, mut right, mut chording) = (false, false, false);
+    for line in lines.filter(|line| !line.is_empty()) {
+        let mut parts = line.split_whitespace();
+        let (Some(time), Some(kind)) = (parts.next(), parts.next()) else {
+            continue;
+        };
+        let Ok(secs) = time.parse::<f64>() else {
+            return Err(invalid(format!("bad time in '{line}'")));
+        };
+        // Only mouse buttons matter. Moves, and lines like `start`, `won` and
+        // `boom`, don't.
+        if !matches!(kind, "lc" | "lr" | "rc" | "rr" | "mc" | "mr") {
+            continue;
+        }
+        let cell = event_cell(parts, cols, rows);
+
+        let action = match kind {
+            "lc" => {
+                left = true;
+                chording = right;
+                None
+            }
+            "rc" => {
+                right = true;
+                chording = left;
+                cell.filter(|_| !chording).map(|(x, y)| Action::Flag(x, y))
+            }
+            "lr" | "rr" => {
+                let was_chording = chording;
+                if kind == "lr" {
+                    left = false;
+                } else {
+                    right = false;
+                }
+                // Only the first release of the two chords.
+                chording = false;
+                if was_chording {
+                    cell.map(|(x, y)| Action::Chord(x, y))
+                } else if kind == "lr" && !right {
+                    cell.map(|(x, y)| Action::Open(x, y))
+                } else {
+                    None
+                }
+            }
+            "mr" => cell.map(|(x, y)| Action::Chord(x, y)),
+            _ => None,
+        };
+        if let Some(action) = action {
+            inputs.push(Input {
+                // Some programs count from before the first click.
+                time: Duration::from_millis((secs.max(0.0) * 1000.0).round() as u64),
+                action,
+            });
+        }
+    }
+
+    Ok(Replay {
+        code: board_code::encode(&board),
+        seed: None,
+        question_marks,
+        inputs,
+    })
+}
+
+/// Works out which cell an event was on, from `x y (col row)`. Events off the
+/// board don't have one.
+fn event_cell<'a>(
+    mut parts: impl Iterator<Item = &'a str>,
+    cols: usize,
+    rows: usize,
+) -> Option<(usize, usize)> {
+    let px: usize = parts.next()?.parse().ok()?;
+    let py: usize = parts.next()?.parse().ok()?;
+    let col = parts
+        .next()
+        .and_then(|s| s.trim_start_matches('(').parse::<usize>().ok());
+    let row = parts
+        .next()
+        .and_then(|s| s.trim_end_matches(')').parse::<usize>().ok());
+    let (x, y) = match (col, row) {
+        (Some(col), Some(row)) => (col.checked_sub(1)?, row.checked_sub(1)?),
+        // Not every program writes the cell, so fall back to the pixels.
+        _ => (px / SQUARE_SIZE, py / SQUARE_SIZE),
+    };
+    (x < cols && y < rows).then_some((x, y))
+}
+
+#[test]
+fn test_rawvf_round_trip() {
+    let mut mines = vec![false; 16].into_boxed_slice();
+    mines[2 * 4 + 2] = true;
+    let board = Board::with_mines(4, 4, mines);
+    let inputs: Vec<Input> = [
+        Action::Open(0, 0),
+        Action::Flag(2, 2),
+        Action::Chord(1, 2),
+        Action::Open(3, 0),
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(i, action)| Input {
+        time: Duration::from_millis(i as u64 * 1250),
+        action,
+    })
+    .collect();
+    let replay = Replay::new(&board, inputs.clone());
+
+    let imported = import(&export(&replay).unwrap()).unwrap();
+    assert_eq!(imported.code, replay.code);
+    assert_eq!(imported.inputs, inputs);
+
+    let with_undo = Replay::new(
+        &board,
+        vec![Input {
+            time: Duration::ZERO,
+            action: Action::Undo,
+        }],
+    );
+    assert!(export(&with_undo).is_err());
+}
+
+#[test]
+fn test_rawvf_import_both_buttons() {
+    let file = "RawVF_Version: Rev7\n\
+                Width: 4\n\
+                Height: 4\n\
+                Mines: 1\n\
+                Board:\n\
+                *000\n\
+                0000\n\
+                0000\n\
+                0000\n\
+                Events:\n\
+                -0.10 start\n\
+                0.00 lc 40 8 (3 1)\n\
+                0.00 lr 40 8 (3 1)\n\
+                0.50 mv 20 10 (2 1)\n\
+                0.60 rc 8 8 (1 1)\n\
+                0.70 rr 8 8 (1 1)\n\
+                1.00 lc 20 8 (2 1)\n\
+                1.10 rc 20 8 (2 1)\n\
+                1.20 lr 20 8 (2 1)\n\
+                1.30 rr 20 8 (2 1)\n\
+                1.40 lc 100 100\n\
+                1.50 lr 100 100\n\
+                1.50 won\n";
+    let replay = import(file).unwrap();
+    let actions: Vec<Action> = replay.inputs.iter().map(|i| i.action).collect();
+    assert_eq!(
+        actions,
+        [Action::Open(2, 0), Action::Flag(0, 0), Action::Chord(1, 0)]
+    );
+    assert_eq!(replay.inputs[2].time, Duration::from_millis(1200));
+
+    assert!(import(&file.replace("*000", "*00")).is_err());
+    assert!(import(&file.replace("Mines: 1", "Mines: 2")).is_err());
+    assert!(import(&file.replace("Width: 4", "Width: 100000")).is_err());
+}
@@ -0,0 +1,323 @@
+//! Best times, kept for each board size and mine count.
+//!
+//! The file is plain text: a header line, then one record per line, as
+//! `cols rows mines time_ms date 3bv clicks effective_clicks name`. The name
+//! is last since it can have spaces. Version 1 files, which didn't have the
+//! 3BV and clicks, can still be read.
+
+use std::{
+    collections::BTreeMap,
+    env, io,
+    time::{Duration, SystemTime},
+};
+
+use crate::board::Board;
+use crate::metrics::Metrics;
+use crate::shared::{self, Config, Lang};
+
+const FILE_NAME: &str = "records.txt";
+const HEADER: &str = "minesweeper records 2";
+const HEADER_V1: &str = "minesweeper records 1";
+/// How many times are kept for each kind of board.
+pub const TOP_N: usize = 10;
+
+/// Which records a game counts towards.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BoardKey {
+    pub cols: usize,
+    pub rows: usize,
+    pub mines: usize,
+}
+
+impl BoardKey {
+    pub fn of(cfg: &Config) -> Self {
+        Self {
+            cols: cfg.cell_cols,
+            rows: cfg.cell_rows,
+            mines: cfg.mine_count,
+        }
+    }
+
+    /// Which records and stats a game counts towards, if any. Only generated
+    /// boards count: anyone could play a known layout, from a board code or
+    /// file, as many times as it takes.
+    pub fn of_game(cfg: &Config, board: &Board) -> Option<Self> {
+        let is_generated = board.seed().is_some() && cfg.code.is_none() && cfg.board.is_none();
+        is_generated.then(|| Self::of(cfg))
+    }
+}
+
+#[derive(Clone)]
+pub struct Record {
+    pub time: Duration,
+    /// As YYYY-MM-DD.
+    pub date: String,
+    pub name: String,
+    /// Records from before these were kept don't have them.
+    pub metrics: Option<Metrics>,
+}
+
+impl Record {
+    /// A record set just now, by whoever is logged in.
+    pub fn new(time: Duration, metrics: Metrics) -> Self {
+        Self {
+            time,
+            date: date_from_unix_days(
+                SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs()
+                    / (24 * 60 * 60),
+            ),
+            name: player_name(),
+            metrics: Some(metrics),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Records {
+    boards: BTreeMap<BoardKey, Vec<Record>>,
+}
+
+impl Records {
+    /// Loads the records, or starts over if they can't be read.
+    pub fn load() -> Self {
+        shared::read_data_file(FILE_NAME)
+            .and_then(|s| s.map_or_else(|| Ok(Self::default()), |s| Self::parse(&s)))
+            .unwrap_or_else(|err| {
+                eprintln!("Couldn't read the records: {err}");
+                Self::default()
+            })
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        shared::write_data_file(FILE_NAME, &self.serialize())
+    }
+
+    /// The best times for a kind of board, fastest first.
+    pub fn get(&self, key: BoardKey) -> &[Record] {
+        self.boards.get(&key).map_or(&[], |records| records)
+    }
+
+    /// Adds a time, if it's good enough. Returns its place, from 0.
+    pub fn add(&mut self, key: BoardKey, record: Record) -> Option<usize> {
+        let records = self.boards.entry(key).or_default();
+        // Ties go to whoever got there first.
+        let place = records.partition_point(|r| r.time <= record.time);
+        if place >= TOP_N {
+            return None;
+        }
+        records.insert(place, record);
+        records.truncate(TOP_N);
+        Some(place)
+    }
+
+    fn serialize(&self) -> String {
+        let mut s = format!("{HEADER}\n");
+        for (key, records) in &self.boards {
+            for r in records {
+                // Old records get zeros, which read back as no metrics.
+                let m = r.metrics.unwrap_or(Metrics {
+                    bbbv: 0,
+                    clicks: 0,
+                    effective_clicks: 0,
+                });
+                s += &format!(
+                    "{} {} {} {} {} {} {} {} {}\n",
+                    key.cols,
+                    key.rows,
+                    key.mines,
+                    r.time.as_millis(),
+                    r.date,
+                    m.bbbv,
+                    m.clicks,
+                    m.effective_clicks,
+                    r.name
+                );
+            }
+        }
+        s
+    }
+
+    fn parse(s: &str) -> io::Result<Self> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+        let mut lines = s.lines();
+        let has_metrics = match lines.next() {
+            Some(HEADER) => true,
+            Some(HEADER_V1) => false,
+            _ => return Err(invalid("not a records file".to_owned())),
+        };
+        let mut records = Self::default();
+        for line in lines.filter(|line| !line.is_empty()) {
+            let bad_line = || invalid(format!("bad line '{line}'"));
+            let mut parts = line.splitn(if has_metrics { 9 } else { 6 }, ' ');
+            let mut num = || -> io::Result<u64> {
+                parts
+                    .next()
+                    .and_then(|part| part.parse().ok())
+                    .ok_or_else(bad_line)
+            };
+            let key = BoardKey {
+                cols: num()? as usize,
+                rows: num()? as usize,
+                mines: num()? as usize,
+            };
+            let time = Duration::from_millis(num()?);
+            let date = parts.next().ok_or_else(bad_line)?.to_owned();
+            let metrics = if has_metrics {
+                let mut num = || -> io::Result<usize> {
+                    parts
+                        .next()
+                        .and_then(|part| part.parse().ok())
+                        .ok_or_else(bad_line)
+                };
+                Some(Metrics {
+                    bbbv: num()?,
+                    clicks: num()?,
+                    effective_clicks: num()?,
+                })
+                .filter(|m| m.bbbv > 0)
+            } else {
+                None
+            };
+            let name = parts.next().unwrap_or_default().to_owned();
+            records.add(
+                key,
+                Record {
+                    time,
+                    date,
+                    name,
+                    metrics,
+                },
+            );
+        }
+        Ok(records)
+    }
+}
+
+/// Lists the saved records for a kind of board, one per line, under a heading.
+pub fn describe(key: BoardKey, lang: Lang) -> String {
+    let heading = match lang {
+        Lang::En => format!("Best times: {}x{}, {} mines", key.cols, key.rows, key.mines),
+        Lang::Jp => format!("最速記録：{}×{}、地雷{}個", key.cols, key.rows, key.mines),
+    };
+    let records = Records::load();
+    let records = records.get(key);
+    if records.is_empty() {
+        let none = lang.en_jp("No records yet.", "まだ記録がありません。");
+        return format!("{heading}\n{none}");
+    }
+    let lines = records
+        .iter()
+        .enumerate()
+        .map(|(i, r)| {
+            let speed = r.metrics.map_or(String::new(), |m| {
+                format!(" ({:.2} 3BV/s)", m.bbbv_per_sec(r.time))
+            });
+            format!(
+                "{}. {:.2}{}{speed} {} {}",
+                i + 1,
+                r.time.as_secs_f64(),
+                lang.en_jp("s", "秒"),
+                r.date,
+                r.name
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    format!("{heading}\n{lines}")
+}
+
+/// Whoever is logged in.
+pub fn player_name() -> String {
+    ["USERNAME", "USER"]
+        .into_iter()
+        .find_map(|var| env::var(var).ok())
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| "Player".to_owned())
+}
+
+/// Converts days since 1970-01-01 to a YYYY-MM-DD date.
+// Based off Howard Hinnant's `civil_from_days`.
+fn date_from_unix_days(days: u64) -> String {
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+#[test]
+fn test_date_from_unix_days() {
+    assert_eq!(date_from_unix_days(0), "1970-01-01");
+    assert_eq!(date_from_unix_days(59), "1970-03-01");
+    assert_eq!(date_from_unix_days(11_016), "2000-02-29");
+    assert_eq!(date_from_unix_days(20_744), "2026-10-18");
+}
+
+#[test]
+fn test_board_key_of_game() {
+    let cfg = Config::default();
+    let board = Board::new(&cfg);
+    assert!(BoardKey::of_game(&cfg, &board).is_some());
+
+    let fixed = Board::with_mines(4, 4, vec![false; 16].into_boxed_slice());
+    assert!(BoardKey::of_game(&cfg, &fixed).is_none());
+    let code = Config {
+        code: Some(crate::board_code::encode(&fixed)),
+        ..Config::default()
+    };
+    assert!(BoardKey::of_game(&code, &crate::board::new_game(&code)).is_none());
+    let file = Config {
+        board: Some(fixed),
+        ..Config::default()
+    };
+    assert!(BoardKey::of_game(&file, &board).is_none());
+}
+
+#[test]
+fn test_records() {
+    let key = BoardKey {
+        cols: 9,
+        rows: 9,
+        mines: 10,
+    };
+    let record = |secs, name: &str| Record {
+        time: Duration::from_secs(secs),
+        date: "2026-10-18".to_owned(),
+        name: name.to_owned(),
+        metrics: Some(Metrics {
+            bbbv: 30,
+            clicks: 40,
+            effective_clicks: 35,
+        }),
+    };
+    let mut records = Records::default();
+    for i in 0..TOP_N as u64 {
+        assert_eq!(records.add(key, record(10 + i, "a")), Some(i as usize));
+    }
+    assert_eq!(records.add(key, record(100, "slow")), None);
+    assert_eq!(records.add(key, record(10, "tie")), Some(1));
+    assert_eq!(records.get(key).len(), TOP_N);
+
+    let loaded = Records::parse(&records.serialize()).unwrap();
+    assert_eq!(loaded.serialize(), records.serialize());
+    assert_eq!(loaded.get(key)[1].name, "tie");
+    assert!(loaded.get(BoardKey { mines: 11, ..key }).is_empty());
+    assert_eq!(loaded.get(key)[0].metrics, record(10, "a").metrics);
+
+    let old = Records::parse("minesweeper records 1\n9 9 10 12345 2020-01-01 Old Name\n").unwrap();
+    let old = &old.get(key)[0];
+    assert_eq!(old.name, "Old Name");
+    assert_eq!(old.metrics, None);
+}
@@ -0,0 +1,409 @@
+//! Recording games and playing them back.
+//!
+//! The file is plain text: a header line, `key value` lines for the board
+//! (always `code`, the board code for the mines, `seed` if there was one, and
+//! `question_marks on` if they were), then one input per line after an `inputs` line, as
+//! `time_ms action [x y]`. Times are from the first click.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::board::Board;
+use crate::{board_code, rawvf, shared};
+
+const HEADER: &str = "minesweeper replay 1";
+
+/// Something the player did to the board.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Action {
+    /// A left click.
+    Open(usize, usize),
+    /// A middle click, or both buttons at once.
+    Chord(usize, usize),
+    /// A right click.
+    Flag(usize, usize),
+    Undo,
+    Redo,
+}
+
+impl Action {
+    /// Does it to the board, the same way the game window would. Returns
+    /// whether anything happened.
+    pub fn apply(self, board: &mut Board) -> bool {
+        match self {
+            Action::Open(x, y) => board.open(x, y),
+            Action::Chord(x, y) => board.chord(x, y),
+            Action::Flag(x, y) => board.toggle_flag(x, y),
+            Action::Undo => board.undo(),
+            Action::Redo => board.redo(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Input {
+    pub time: Duration,
+    pub action: Action,
+}
+
+pub struct Replay {
+    /// The mines at the end of the game. Anti-guess can move a mine after the
+    /// first click, but never in a way that changes a number already seen, so
+    /// earlier clicks play out the same. Games where they were placed twice
+    /// aren't recorded (see `play`).
+    pub code: String,
+    pub seed: Option<u64>,
+    /// Whether flags turned into question marks, which changes what the
+    /// flag inputs do.
+    pub question_marks: bool,
+    pub inputs: Vec<Input>,
+}
+
+impl Replay {
+    /// Starts a replay of `board`'s game. The mines need to be placed already.
+    pub fn new(board: &Board, inputs: Vec<Input>) -> Self {
+        Self {
+            code: board_code::encode(board),
+            seed: board.seed(),
+            question_marks: board.question_marks(),
+            inputs,
+        }
+    }
+
+    /// The board the game started with, before any clicks.
+    pub fn board(&self) -> Board {
+        let mut board = board_code::decode(&self.code).expect("checked when the replay was parsed");
+        board.set_question_marks(self.question_marks);
+        board
+    }
+
+    pub fn serialize(&self) -> String {
+        let mut s = format!("{HEADER}\ncode {}\n", self.code);
+        if let Some(seed) = self.seed {
+            s += &format!("seed {seed}\n");
+        }
+        if self.question_marks {
+            s += "question_marks on\n";
+        }
+        s += "inputs\n";
+        s += &serialize_inputs(&self.inputs);
+        s
+    }
+
+    pub fn parse(s: &str) -> io::Result<Self> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+        let mut lines = s.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(invalid("not a replay".to_owned()));
+        }
+
+        let mut code = None;
+        let mut seed = None;
+        let mut question_marks = false;
+        for line in lines.by_ref() {
+            if line == "inputs" {
+                break;
+            }
+            let (key, value) = line
+                .split_once(' ')
+                .ok_or_else(|| invalid(format!("bad line '{line}'")))?;
+            match key {
+                "code" => code = Some(value.to_owned()),
+                "seed" => {
+                    seed = Some(
+                        value
+                            .parse()
+                            .map_err(|_| invalid(format!("bad number in '{line}'")))?,
+                    )
+                }
+                "question_marks" => question_marks = value == "on",
+                // Probably from a newer version; skip it.
+                _ => {}
+            }
+        }
+        let code = code.ok_or_else(|| invalid("there's no board code".to_owned()))?;
+        let board =
+            board_code::decode(&code).map_err(|err| invalid(format!("bad board code: {err}")))?;
+        let inputs = parse_inputs(lines, board.cols(), board.rows())?;
+        Ok(Self {
+            code,
+            seed,
+            question_marks,
+            inputs,
+        })
+    }
+}
+
+/// Does something to the board, keeping track of it in `inputs` for the
+/// replay. Returns whether anything happened.
+///
+/// Undoing the first click takes the mines away, and the next one places them
+/// again somewhere else, so no one layout fits the whole game. Recording stops
+/// there, leaving `inputs` as `None`.
+pub fn play(
+    board: &mut Board,
+    inputs: &mut Option<Vec<Input>>,
+    time: Duration,
+    action: Action,
+) -> bool {
+    // Clicks after the end don't reach the game, but undoing it does.
+    if !board.is_game_over() || matches!(action, Action::Undo | Action::Redo) {
+        if let Some(inputs) = inputs {
+            inputs.push(Input { time, action });
+        }
+    }
+    let mines_placed = board.mines_placed();
+    let changed = action.apply(board);
+    if mines_placed && !board.mines_placed() {
+        *inputs = None;
+    }
+    changed
+}
+
+/// Writes inputs one per line, as in a replay file.
+pub fn serialize_inputs(inputs: &[Input]) -> String {
+    let mut s = String::new();
+    for input in inputs {
+        let ms = input.time.as_millis();
+        s += &match input.action {
+            Action::Open(x, y) => format!("{ms} open {x} {y}\n"),
+            Action::Chord(x, y) => format!("{ms} chord {x} {y}\n"),
+            Action::Flag(x, y) => format!("{ms} flag {x} {y}\n"),
+            Action::Undo => format!("{ms} undo\n"),
+            Action::Redo => format!("{ms} redo\n"),
+        };
+    }
+    s
+}
+
+/// Reads inputs written by `serialize_inputs`, for a board of the given size.
+pub fn parse_inputs<'a>(
+    lines: impl Iterator<Item = &'a str>,
+    cols: usize,
+    rows: usize,
+) -> io::Result<Vec<Input>> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+    let mut inputs = Vec::new();
+    for line in lines.filter(|line| !line.is_empty()) {
+        let bad_line = || invalid(format!("bad line '{line}'"));
+        let parts: Vec<&str> = line.split(' ').collect();
+        let num = |i: usize| -> io::Result<usize> {
+            parts
+                .get(i)
+                .and_then(|part| part.parse().ok())
+                .ok_or_else(bad_line)
+        };
+        let cell = || -> io::Result<(usize, usize)> {
+            let (x, y) = (num(2)?, num(3)?);
+            if x >= cols || y >= rows {
+                return Err(invalid(format!("'{line}' is off the board")));
+            }
+            Ok((x, y))
+        };
+        let action = match parts.get(1).copied() {
+            Some("open") => {
+                let (x, y) = cell()?;
+                Action::Open(x, y)
+            }
+            Some("chord") => {
+                let (x, y) = cell()?;
+                Action::Chord(x, y)
+            }
+            Some("flag") => {
+                let (x, y) = cell()?;
+                Action::Flag(x, y)
+            }
+            Some("undo") => Action::Undo,
+            Some("redo") => Action::Redo,
+            _ => return Err(bad_line()),
+        };
+        let time = Duration::from_millis(num(0)? as u64);
+        inputs.push(Input { time, action });
+    }
+    Ok(inputs)
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Speed {
+    #[default]
+    Normal,
+    Double,
+    /// Everything at once.
+    Instant,
+}
+
+/// Plays a replay back onto a board, in time.
+pub struct Playback {
+    replay: Replay,
+    /// How many inputs have been played.
+    next: usize,
+    /// How far into the game it is.
+    time: Duration,
+    pub speed: Speed,
+}
+
+impl Playback {
+    pub fn new(replay: Replay, speed: Speed) -> Self {
+        Self {
+            replay,
+            next: 0,
+            time: Duration::ZERO,
+            speed,
+        }
+    }
+
+    /// Goes back to the start. Returns the board to play onto.
+    pub fn restart(&mut self) -> Board {
+        self.next = 0;
+        self.time = Duration::ZERO;
+        self.replay.board()
+    }
+
+    /// Moves the game along by `real_time`, going by the speed, and plays any
+    /// inputs that are due. Returns whether there were any.
+    pub fn advance(&mut self, real_time: Duration, board: &mut Board) -> bool {
+        let end = self.replay.inputs.last().map_or(Duration::ZERO, |i| i.time);
+        self.time = match self.speed {
+            Speed::Normal => self.time + real_time,
+            Speed::Double => self.time + real_time * 2,
+            // Straight to the end, which leaves room to slow down again.
+            Speed::Instant => self.time.max(end),
+        };
+        let start = self.next;
+        while let Some(input) = self.replay.inputs.get(self.next) {
+            if input.time > self.time {
+                break;
+            }
+            input.action.apply(board);
+            self.next += 1;
+        }
+        self.next > start
+    }
+
+    /// How far into the game it is, stopping at the last input.
+    pub fn time(&self) -> Duration {
+        let end = self.replay.inputs.last().map_or(Duration::ZERO, |i| i.time);
+        self.time.min(end)
+    }
+}
+
+/// Writes a new replay file in the data directory. Returns where it went.
+pub fn write(replay: &Replay) -> io::Result<PathBuf> {
+    shared::write_new_data_file("replay", "txt", &replay.serialize())
+}
+
+/// Like `write`, but as RAWVF for other programs.
+pub fn export_rawvf(replay: &Replay) -> io::Result<PathBuf> {
+    let s =
+        rawvf::export(replay).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    shared::write_new_data_file("replay", "rawvf", &s)
+}
+
+/// Reads one of our replays, or a RAWVF file if it ends in `.rawvf`.
+pub fn read(path: &Path) -> io::Result<Replay> {
+    let s = fs::read_to_string(path)?;
+    if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("rawvf"))
+    {
+        rawvf::import(&s)
+    } else {
+        Replay::parse(&s)
+    }
+}
+
+#[test]
+fn test_replay_round_trip() {
+    use crate::board::GameState;
+
+    // Same board as in `test_flag_chord_and_win`.
+    let mut mines = vec![false; 16].into_boxed_slice();
+    mines[2 * 4 + 2] = true;
+    let mut board = Board::with_mines(4, 4, mines);
+    let inputs: Vec<Input> = [
+        Action::Open(0, 0),
+        Action::Open(0, 0),
+        Action::Flag(3, 3),
+        Action::Undo,
+        Action::Flag(2, 2),
+        Action::Chord(1, 2),
+        Action::Chord(2, 1),
+        Action::Chord(2, 3),
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(i, action)| Input {
+        time: Duration::from_millis(i as u64 * 250),
+        action,
+    })
+    .collect();
+    for input in &inputs {
+        input.action.apply(&mut board);
+    }
+    assert_eq!(board.state(), GameState::Won);
+
+    let replay = Replay::new(&board, inputs.clone());
+    let loaded = Replay::parse(&replay.serialize()).unwrap();
+    assert_eq!(loaded.inputs, inputs);
+    let mut replayed = loaded.board();
+    for input in &loaded.inputs {
+        input.action.apply(&mut replayed);
+    }
+    assert_eq!(replayed.cells(), board.cells());
+    assert_eq!(replayed.clicks(), board.clicks());
+    assert_eq!(replayed.state(), GameState::Won);
+
+    let mut playback = Playback::new(loaded, Speed::Double);
+    let mut played = playback.restart();
+    assert!(playback.advance(Duration::from_millis(300), &mut played));
+    // 600ms in, so the first three inputs
+    assert_eq!(played.clicks().total, 3);
+    assert!(!playback.advance(Duration::from_millis(10), &mut played));
+    playback.speed = Speed::Instant;
+    assert!(playback.advance(Duration::ZERO, &mut played));
+    assert_eq!(played.state(), GameState::Won);
+    assert_eq!(playback.time(), Duration::from_millis(7 * 250));
+    playback.speed = Speed::Normal;
+    assert!(!playback.advance(Duration::from_millis(10), &mut played));
+    assert_eq!(playback.time(), Duration::from_millis(7 * 250));
+
+    assert!(Replay::parse("something else").is_err());
+    assert!(Replay::parse(&replay.serialize().replace("open 0 0", "open 9 0")).is_err());
+}
+
+#[test]
+fn test_undoing_the_first_click_stops_recording() {
+    use crate::shared::Config;
+
+    let mut board = Board::new(&Config {
+        seed: Some(3),
+        ..Config::default()
+    });
+    let mut inputs = Some(Vec::new());
+    for action in [
+        Action::Flag(9, 9),
+        Action::Undo,
+        Action::Open(0, 0),
+        Action::Flag(9, 9),
+        Action::Undo,
+    ] {
+        assert!(play(&mut board, &mut inputs, Duration::ZERO, action));
+    }
+    assert_eq!(inputs.as_ref().map(Vec::len), Some(5));
+
+    // The mines go with the first click, and could be placed differently the
+    // next time.
+    assert!(play(&mut board, &mut inputs, Duration::ZERO, Action::Undo));
+    assert!(!board.mines_placed());
+    assert!(inputs.is_none());
+    assert!(play(
+        &mut board,
+        &mut inputs,
+        Duration::ZERO,
+        Action::Open(5, 5)
+    ));
+    assert!(inputs.is_none());
+}
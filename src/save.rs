@@ -0,0 +1,253 @@
+//! Saving a game in progress, so it can be resumed later.
+//!
+//! The file is plain text: a header line, `key value` lines, then the board
+//! after a `board` line, as in a board file (see `board_file`). The digits
+//! are only for people reading it.
+//!
+//! The inputs so far come last, after an `inputs` line, as in a replay file.
+//! Games that can't be replayed don't have the line.
+
+use std::{io, time::Duration};
+
+use crate::board::{Board, Clicks};
+use crate::board_file;
+use crate::replay::{self, Input};
+use crate::shared::{self, Config};
+
+const FILE_NAME: &str = "save.txt";
+const HEADER: &str = "minesweeper save 1";
+
+pub struct SavedGame {
+    pub board: Board,
+    pub no_guess: bool,
+    pub elapsed: Duration,
+    pub hints_used: usize,
+    /// For the replay. `None` if it can't be replayed (see `replay::play`).
+    pub inputs: Option<Vec<Input>>,
+}
+
+pub fn exists() -> bool {
+    shared::data_dir().is_some_and(|dir| dir.join(FILE_NAME).exists())
+}
+
+pub fn write(game: &SavedGame) -> io::Result<()> {
+    shared::write_data_file(FILE_NAME, &to_string(game))
+}
+
+pub fn read() -> io::Result<SavedGame> {
+    match shared::read_data_file(FILE_NAME)? {
+        Some(s) => parse(&s),
+        None => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "there isn't a saved game",
+        )),
+    }
+}
+
+/// Deletes the save, if there is one.
+pub fn delete() {
+    if let Err(err) = shared::delete_data_file(FILE_NAME) {
+        eprintln!("Couldn't delete the saved game: {err}");
+    }
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+fn to_string(game: &SavedGame) -> String {
+    let board = &game.board;
+    let mut s = format!(
+        "{HEADER}\n\
+         cols {}\n\
+         rows {}\n\
+         mines {}\n\
+         no_guess {}\n\
+         started {}\n\
+         moves {}\n\
+         undos {}\n\
+         hints {}\n\
+         clicks {}\n\
+         effective_clicks {}\n\
+         elapsed_ms {}\n",
+        board.cols(),
+        board.rows(),
+        board.mine_count(),
+        u8::from(game.no_guess),
+        u8::from(board.has_started()),
+        board.move_count(),
+        board.undo_count(),
+        game.hints_used,
+        board.clicks().total,
+        board.clicks().effective,
+        game.elapsed.as_millis(),
+    );
+    // Fixed layouts don't have one.
+    if let Some(seed) = board.seed() {
+        s += &format!("seed {seed}\n");
+    }
+    s += "board\n";
+    s += &board_file::serialize(board);
+    if let Some(inputs) = &game.inputs {
+        s += "inputs\n";
+        s += &replay::serialize_inputs(inputs);
+    }
+    s
+}
+
+fn parse(s: &str) -> io::Result<SavedGame> {
+    let mut lines = s.lines();
+    if lines.next() != Some(HEADER) {
+        return Err(invalid("not a saved game"));
+    }
+
+    let mut cfg = Config::default();
+    let (mut moves, mut undos, mut hints, mut elapsed_ms) = (0, 0, 0, 0);
+    let mut started = None;
+    let mut clicks = Clicks::default();
+    for line in lines.by_ref() {
+        if line == "board" {
+            break;
+        }
+        let (key, value) = line
+            .split_once(' ')
+            .ok_or_else(|| invalid(format!("bad line '{line}'")))?;
+        let value: u64 = value
+            .parse()
+            .map_err(|_| invalid(format!("bad number in '{line}'")))?;
+        match key {
+            "cols" => cfg.cell_cols = value as usize,
+            "rows" => cfg.cell_rows = value as usize,
+            "mines" => cfg.mine_count = value as usize,
+            "no_guess" => cfg.no_guess = value != 0,
+            "seed" => cfg.seed = Some(value),
+            "started" => started = Some(value != 0),
+            "moves" => moves = value as usize,
+            "undos" => undos = value as usize,
+            "hints" => hints = value as usize,
+            "clicks" => clicks.total = value as usize,
+            "effective_clicks" => clicks.effective = value as usize,
+            "elapsed_ms" => elapsed_ms = value,
+            // Probably from a newer version; skip it.
+            _ => {}
+        }
+    }
+
+    // Before anything's made that big. Fixed layouts, which don't have seeds,
+    // bring their own mines, so only their size has to fit.
+    Config {
+        cell_cols: cfg.cell_cols,
+        cell_rows: cfg.cell_rows,
+        mine_count: if cfg.seed.is_some() { cfg.mine_count } else { 0 },
+        ..Config::default()
+    }
+    .validate()
+    .map_err(invalid)?;
+
+    let mut mines = Vec::with_capacity(cfg.cell_cols * cfg.cell_rows);
+    let mut cells = Vec::with_capacity(cfg.cell_rows);
+    for line in lines.by_ref().take(cfg.cell_rows) {
+        let (row, row_mines) = board_file::parse_row(line)?;
+        if row.len() != cfg.cell_cols {
+            return Err(invalid("row is the wrong length"));
+        }
+        cells.push(row);
+        mines.extend(row_mines);
+    }
+    if cells.len() != cfg.cell_rows {
+        return Err(invalid("not enough rows"));
+    }
+
+    let mine_total = mines.iter().filter(|&&b| b).count();
+    let mines = if mine_total == 0 && moves == 0 {
+        // Not placed yet
+        None
+    } else if mine_total == cfg.mine_count {
+        Some(mines.into_boxed_slice())
+    } else {
+        return Err(invalid("wrong number of mines"));
+    };
+
+    // Saves from before inputs were kept don't have them either.
+    let inputs = match lines.next() {
+        Some("inputs") => Some(replay::parse_inputs(lines, cfg.cell_cols, cfg.cell_rows)?),
+        _ => None,
+    };
+
+    Ok(SavedGame {
+        // Saves from before this was kept had started once there were moves.
+        board: Board::restore(
+            &cfg,
+            mines,
+            cells,
+            started.unwrap_or(moves > 0),
+            moves,
+            undos,
+            clicks,
+        ),
+        no_guess: cfg.no_guess,
+        elapsed: Duration::from_millis(elapsed_ms),
+        hints_used: hints,
+        inputs,
+    })
+}
+
+#[test]
+fn test_save_round_trip() {
+    use crate::replay::Action;
+
+    let cfg = Config {
+        cell_cols: 9,
+        cell_rows: 7,
+        mine_count: 8,
+        seed: Some(1),
+        ..Config::default()
+    };
+    let mut board = Board::new(&cfg);
+    let game = SavedGame {
+        board: board.clone(),
+        no_guess: true,
+        elapsed: Duration::from_millis(1234),
+        hints_used: 2,
+        inputs: None,
+    };
+    // Before the first click, there aren't any mines to save.
+    let loaded = parse(&to_string(&game)).unwrap();
+    assert!(!loaded.board.mines_placed());
+    assert!(!loaded.board.has_started());
+
+    let inputs = vec![
+        Input {
+            time: Duration::ZERO,
+            action: Action::Open(4, 3),
+        },
+        Input {
+            time: Duration::from_millis(500),
+            action: Action::Flag(0, 0),
+        },
+    ];
+    for input in &inputs {
+        input.action.apply(&mut board);
+    }
+    let game = SavedGame {
+        board,
+        inputs: Some(inputs),
+        ..game
+    };
+    let s = to_string(&game);
+    let loaded = parse(&s).unwrap();
+    assert_eq!(to_string(&loaded), s);
+    assert_eq!(loaded.board.cells(), game.board.cells());
+    assert_eq!(loaded.board.move_count(), 1);
+    assert!(loaded.board.has_started());
+    assert_eq!(loaded.board.clicks(), game.board.clicks());
+    assert_eq!(loaded.elapsed, Duration::from_millis(1234));
+    assert_eq!(loaded.inputs, game.inputs);
+    assert!(loaded.no_guess);
+
+    assert!(parse("something else").is_err());
+    assert!(parse(&s.replace("mines 8", "mines 9")).is_err());
+    assert!(parse(&s.replace("cols 9", "cols 0")).is_err());
+    assert!(parse(&s.replace("rows 7", "rows 18446744073709551615")).is_err());
+    assert!(parse(&s.replace("mines 8", "mines 60")).is_err());
+}
@@ -1,30 +1,41 @@
 use ab_glyph::{Font, FontRef, Glyph, PxScaleFont, ScaleFont};
 use glam::IVec2;
-use minifb::{Key, Window};
+use minifb::{Key, KeyRepeat, Window};
 use std::{
-    collections::HashMap,
     mem,
     time::{Duration, Instant},
 };
 
-use crate::{game_window, shared, text};
+use crate::{bindings, board, records, save, shared, stats, text, theme};
+use bindings::{Action, Bindings};
+use save::SavedGame;
 use shared::{Config, Lang};
+use theme::Theme;
 
-const SAFE_CELLS_FOR_FIRST_CLICK: i32 = game_window::SAFE_CELLS_FOR_FIRST_CLICK as i32;
+const SAFE_CELLS_FOR_FIRST_CLICK: i32 = board::SAFE_CELLS_FOR_FIRST_CLICK as i32;
 
-// 4 is the minimum that doesn't crash :)
-const MIN_COLS: i32 = 4;
-const MIN_ROWS: i32 = 4;
-const MAX_COLS: i32 = 99;
-const MAX_ROWS: i32 = 99;
+const MIN_COLS: i32 = shared::MIN_COLS as i32;
+const MIN_ROWS: i32 = shared::MIN_ROWS as i32;
+const MAX_COLS: i32 = shared::MAX_COLS as i32;
+const MAX_ROWS: i32 = shared::MAX_ROWS as i32;
 
-const WINDOW_WIDTH: usize = 300;
-const WINDOW_HEIGHT: usize = 300;
+// Any number this long fits in a u64.
+const MAX_SEED_DIGITS: usize = 19;
+// Codes can be long, so only the end is shown.
+const CODE_INPUT_WIDTH: usize = 12;
+// Long lists of bindings are cut off after this many characters.
+const BINDINGS_TEXT_WIDTH: usize = 26;
+const ACTIONS_PER_PAGE: usize = 4;
+
+const WINDOW_WIDTH: usize = 360;
+const WINDOW_HEIGHT: usize = 540;
 const WINDOW_PADDING: i32 = 5;
 const WINDOW_TITLE_EN: &str = "Minesweeper - Setup";
 const WINDOW_TITLE_JP: &str = "マインスイーパ - 設定";
 
-pub fn run(old_cfg: Config) -> Option<Config> {
+/// Returns the settings for the next game, and the saved game to resume if
+/// the player chose to.
+pub fn run(old_cfg: Config) -> Option<(Config, Option<SavedGame>)> {
     let font_en = FontRef::try_from_slice(shared::FIRA_CODE_BYTES).unwrap();
     let font_en = font_en.as_scaled(20.0);
     let font_jp = FontRef::try_from_slice(shared::NOTO_SANS_JP_BYTES).unwrap();
@@ -34,6 +45,21 @@ pub fn run(old_cfg: Config) -> Option<Config> {
     let mut rows: i32 = old_cfg.cell_rows as i32;
     let mut cols: i32 = old_cfg.cell_cols as i32;
     let mut mine_count: i32 = old_cfg.mine_count as i32;
+    let mut no_guess = old_cfg.no_guess;
+    let mut infinite = old_cfg.infinite;
+    let mut seed = old_cfg
+        .seed
+        .map(|seed| seed.to_string())
+        .unwrap_or_default();
+    let mut code = old_cfg.code.unwrap_or_default();
+    let mut code_is_bad = false;
+    let mut saved_game = if save::exists() {
+        save::read()
+            .map_err(|err| eprintln!("Couldn't read the saved game: {err}"))
+            .ok()
+    } else {
+        None
+    };
 
     let mut gui = GuiState {
         window: Window::new(
@@ -45,6 +71,7 @@ pub fn run(old_cfg: Config) -> Option<Config> {
         .unwrap(),
         buffer: vec![0u32; WINDOW_WIDTH * WINDOW_HEIGHT],
         buffer_width: WINDOW_WIDTH,
+        theme: old_cfg.theme,
 
         mouse_pos: None,
         _mouse_was_oob: false,
@@ -66,9 +93,35 @@ pub fn run(old_cfg: Config) -> Option<Config> {
     };
     let mut prev_buffer = gui.buffer.clone();
 
+    let mut bindings = Bindings::load();
+
     let mut start_game = false;
+    let mut page = Page::Settings;
+    let mut resume_game = false;
     let mut needs_update = true;
-    'window_loop: while gui.window.is_open() && !gui.window.is_key_down(Key::Escape) {
+    'window_loop: while gui.window.is_open() {
+        if let Page::Bindings {
+            capturing: capturing @ Some(_),
+            ..
+        } = &mut page
+        {
+            // Modifiers on their own aren't keys, so this waits for the key
+            // they're held with.
+            let key = gui
+                .window
+                .get_keys_pressed(KeyRepeat::No)
+                .into_iter()
+                .find(|key| bindings::KEYS.contains(key));
+            if let (Some(key), Some(action)) = (key, capturing.take()) {
+                bindings.set_key(action, key, bindings::mods_down(&gui.window));
+                needs_update = true;
+            }
+        } else if bindings
+            .pressed(&gui.window, &[Action::CloseWindow])
+            .is_some()
+        {
+            break;
+        }
         let was_input = gui.update_input();
         needs_update |= was_input | gui.text_cursor_needs_update();
 
@@ -79,9 +132,132 @@ pub fn run(old_cfg: Config) -> Option<Config> {
                 break 'update_buffer;
             }
 
-            gui.buffer.fill(shared::COLOR_MESSAGE_BOX);
+            gui.buffer.fill(gui.theme.message_box);
             gui.caret = gui.caret_start;
 
+            let key = records::BoardKey {
+                cols: cols as usize,
+                rows: rows as usize,
+                mines: mine_count as usize,
+            };
+            if let Page::Records(text) | Page::Stats(text) = &page {
+                let is_stats = matches!(page, Page::Stats(_));
+                gui.label(text.as_str());
+                gui.new_line();
+                if gui.button(lang.en_jp("Back", "戻る")) {
+                    page = Page::Settings;
+                    break 'update_buffer;
+                }
+                if is_stats && gui.button(lang.en_jp("Reset", "リセット")) {
+                    if let Err(err) = stats::Stats::reset() {
+                        eprintln!("Couldn't reset the stats: {err}");
+                    }
+                    page = Page::Stats(stats::describe(key, lang));
+                    break 'update_buffer;
+                }
+                prev_buffer.copy_from_slice(&gui.buffer);
+                needs_update = false;
+                break 'update_buffer;
+            }
+            if let Page::Bindings {
+                page_index,
+                capturing,
+            } = &mut page
+            {
+                if let Some(action) = *capturing {
+                    gui.label(
+                        match lang {
+                            Lang::En => format!("Press a new key for\n{}.", action.name(lang)),
+                            Lang::Jp => {
+                                format!("「{}」の新しいキーを押してください。", action.name(lang))
+                            }
+                        }
+                        .as_str(),
+                    );
+                    gui.new_line();
+                    if gui.button(lang.en_jp("Cancel", "キャンセル")) {
+                        *capturing = None;
+                        break 'update_buffer;
+                    }
+                    prev_buffer.copy_from_slice(&gui.buffer);
+                    needs_update = false;
+                    break 'update_buffer;
+                }
+
+                let page_count = bindings::ACTIONS.len().div_ceil(ACTIONS_PER_PAGE);
+                gui.label(lang.en_jp(
+                    "Click an action to change its key.",
+                    "キーを変える操作をクリック。",
+                ));
+                gui.new_line();
+                for &(action, ..) in bindings::ACTIONS
+                    .iter()
+                    .skip(*page_index * ACTIONS_PER_PAGE)
+                    .take(ACTIONS_PER_PAGE)
+                {
+                    if gui.button(action.name(lang)) {
+                        *capturing = Some(action);
+                        break 'update_buffer;
+                    }
+                    gui.new_line();
+                    let mut text = bindings.describe(action);
+                    if text.chars().count() > BINDINGS_TEXT_WIDTH {
+                        text = text.chars().take(BINDINGS_TEXT_WIDTH - 3).collect();
+                        text += "...";
+                    }
+                    gui.label(text.as_str());
+                    gui.new_line();
+                }
+
+                if gui.button(lang.en_jp("Right-handed", "右利き")) {
+                    bindings = Bindings::default();
+                    break 'update_buffer;
+                }
+                if gui.button(lang.en_jp("Left-handed", "左利き")) {
+                    bindings = Bindings::left_handed();
+                    break 'update_buffer;
+                }
+                gui.new_line();
+                if gui.button("<") {
+                    *page_index = (*page_index + page_count - 1) % page_count;
+                    break 'update_buffer;
+                }
+                gui.label(format!("{}/{page_count}", *page_index + 1).as_str());
+                if gui.button(">") {
+                    *page_index = (*page_index + 1) % page_count;
+                    break 'update_buffer;
+                }
+                if gui.button(lang.en_jp("Save", "保存")) {
+                    if let Err(err) = bindings.save() {
+                        eprintln!("Couldn't save the bindings: {err}");
+                    }
+                    page = Page::Settings;
+                    break 'update_buffer;
+                }
+                if gui.button(lang.en_jp("Cancel", "キャンセル")) {
+                    bindings = Bindings::load();
+                    page = Page::Settings;
+                    break 'update_buffer;
+                }
+                prev_buffer.copy_from_slice(&gui.buffer);
+                needs_update = false;
+                break 'update_buffer;
+            }
+
+            if saved_game.is_some() {
+                gui.label(lang.en_jp("Resume last game?", "前回のゲームを再開？"));
+                if gui.button(lang.en_jp("Resume", "再開")) {
+                    resume_game = true;
+                    break 'window_loop;
+                }
+                if gui.button(lang.en_jp("Discard", "破棄")) {
+                    save::delete();
+                    saved_game = None;
+                    break 'update_buffer;
+                }
+                gui.new_line();
+            }
+
             gui.label(lang.en_jp("Language:", "言語："));
             let mut lang_btn = 0;
             if gui.button_set(
@@ -119,10 +295,80 @@ pub fn run(old_cfg: Config) -> Option<Config> {
             }
             gui.new_line();
 
+            gui.label(lang.en_jp("No guessing:", "運任せなし："));
+            if gui.button(if no_guess {
+                lang.en_jp("On", "オン")
+            } else {
+                lang.en_jp("Off", "オフ")
+            }) {
+                no_guess = !no_guess;
+                break 'update_buffer;
+            }
+            gui.new_line();
+
+            // The size and mines still set how dense it is.
+            gui.label(lang.en_jp("No edges:", "無限盤面："));
+            if gui.button(if infinite {
+                lang.en_jp("On", "オン")
+            } else {
+                lang.en_jp("Off", "オフ")
+            }) {
+                infinite = !infinite;
+                break 'update_buffer;
+            }
+            gui.new_line();
+
+            gui.label(lang.en_jp("Seed:", "シード："));
+            if gui.text_input(&mut seed, MAX_SEED_DIGITS, MAX_SEED_DIGITS, |c| {
+                c.is_ascii_digit()
+            }) {
+                break 'update_buffer;
+            }
+            gui.new_line();
+
+            gui.label(lang.en_jp("Board code:", "盤面コード："));
+            if gui.text_input(&mut code, CODE_INPUT_WIDTH, usize::MAX, |c| {
+                c.is_ascii_alphanumeric()
+            }) {
+                code_is_bad = false;
+                break 'update_buffer;
+            }
+            gui.new_line();
+
             if gui.button(lang.en_jp("Start Game", "プレイ")) {
+                // Leaving it blank is fine.
+                if !code.is_empty() {
+                    let code_cfg = Config {
+                        code: Some(code.clone()),
+                        ..Config::default()
+                    };
+                    if let Err(err) = code_cfg.validate() {
+                        eprintln!("Couldn't use the board code: {err}");
+                        code_is_bad = true;
+                        break 'update_buffer;
+                    }
+                }
                 start_game = true;
                 break 'window_loop;
             }
+            if gui.button(lang.en_jp("Records", "記録")) {
+                page = Page::Records(records::describe(key, lang));
+                break 'update_buffer;
+            }
+            if gui.button(lang.en_jp("Statistics", "統計")) {
+                page = Page::Stats(stats::describe(key, lang));
+                break 'update_buffer;
+            }
+            if gui.button(lang.en_jp("Controls", "操作")) {
+                page = Page::Bindings {
+                    page_index: 0,
+                    capturing: None,
+                };
+                break 'update_buffer;
+            }
+            if code_is_bad {
+                gui.label(lang.en_jp("Bad code", "無効なコード"));
+            }
 
             prev_buffer.copy_from_slice(&gui.buffer);
             needs_update = false;
@@ -133,19 +379,43 @@ pub fn run(old_cfg: Config) -> Option<Config> {
             .unwrap();
     }
 
-    if !start_game {
+    if !start_game && !resume_game {
         return None;
     }
 
     // The window implicitly closes.
 
-    Some(Config {
+    let cfg = Config {
         lang,
         cell_cols: cols.try_into().unwrap(),
         cell_rows: rows.try_into().unwrap(),
         mine_count: mine_count.try_into().unwrap(),
+        no_guess,
+        infinite,
+        question_marks: old_cfg.question_marks,
+        theme: old_cfg.theme,
+        color_blind: old_cfg.color_blind,
+        // Blank means random.
+        seed: seed.parse().ok(),
+        code: Some(code).filter(|code| !code.is_empty()),
         ..Config::default()
-    })
+    };
+    Some((cfg, saved_game.filter(|_| resume_game)))
+}
+
+/// What the window is showing.
+enum Page {
+    Settings,
+    /// The best times for the selected board.
+    Records(String),
+    /// The stats for the selected board.
+    Stats(String),
+    /// The key and mouse bindings, a few actions at a time.
+    Bindings {
+        page_index: usize,
+        /// The action waiting for its new key.
+        capturing: Option<Action>,
+    },
 }
 
 const BORDER_SIZE: i32 = 2;
@@ -169,6 +439,7 @@ struct GuiState<'f> {
     window: minifb::Window,
     buffer: Vec<u32>,
     buffer_width: usize,
+    theme: Theme,
 
     /// The current mouse position. Present if there was a click (now or on the previous frame) and it was within the window.
     mouse_pos: Option<IVec2>,
@@ -182,9 +453,9 @@ struct GuiState<'f> {
 
     next_widget_id: u16,
     /// The currently active input field.
-    active_input: Option<ActiveInput<i32>>,
+    active_input: Option<ActiveInput<String>>,
     /// The soon-to-be-formerly active input field.
-    inactive_input: Option<ActiveInput<i32>>,
+    inactive_input: Option<ActiveInput<String>>,
 
     font: GuiFontRef<'f>,
     font_en: GuiFontRef<'f>,
@@ -323,7 +594,7 @@ impl<'f> GuiState<'f> {
                     x: 0,
                     y: BORDER_SIZE + BUTTON_PADDING_VERTICAL,
                 },
-            shared::COLOR_BUTTON_TEXT,
+            self.theme.button_text,
         );
 
         self.caret.x += outer_size.x + self.padding.x;
@@ -336,7 +607,7 @@ impl<'f> GuiState<'f> {
         let id = self.take_id();
         if let Some(inactive_input) = self.inactive_input.as_ref() {
             if inactive_input.id == id {
-                *num = inactive_input.text;
+                *num = parse_num_text(&inactive_input.text, *num);
                 self.inactive_input = None;
                 return true;
             }
@@ -345,8 +616,10 @@ impl<'f> GuiState<'f> {
         if let Some(mut active_input) = self.active_input.clone() {
             if active_input.id == id {
                 let draw_cursor = active_input.text_cursor_blink();
-                let mutated = self._number_input(&mut active_input.text, id, true, draw_cursor);
+                let mut active_num = parse_num_text(&active_input.text, *num);
+                let mutated = self._number_input(&mut active_num, id, true, draw_cursor);
                 if mutated {
+                    active_input.text = active_num.to_string();
                     self.active_input = Some(active_input);
                     return true;
                 }
@@ -367,117 +640,185 @@ impl<'f> GuiState<'f> {
             return true;
         }
 
-        {
-            let string = num.to_string();
-            let text = string.as_str().into();
-            let font = self.font_for(text);
-            let mut glyphs = Vec::new();
-            let glyphs_size = text::layout_paragraph(font, f32::INFINITY, text.str, &mut glyphs);
-
-            static PADDING: IVec2 = IVec2 {
-                x: BUTTON_PADDING_HORIZONTAL,
-                y: BUTTON_PADDING_VERTICAL,
-            };
-            static HALF_PADDING: IVec2 = IVec2 {
-                x: PADDING.x / 2,
-                y: PADDING.y / 2,
-            };
-            // This may be different if the padding is an odd number.
-            static OTHER_HALF_PADDING: IVec2 = IVec2 {
-                x: BUTTON_PADDING_HORIZONTAL - HALF_PADDING.x,
-                y: BUTTON_PADDING_VERTICAL - HALF_PADDING.y,
-            };
-            let bordered_contents_size = glyphs_size + PADDING;
-            let bordered_size = bordered_contents_size + IVec2::splat(BORDER_SIZE * 2);
-            let outer_size = bordered_size + PADDING;
-            self.wrap_if_needed(outer_size);
-
-            let click_bounds = (self.caret, self.caret + outer_size);
-            if !is_active && self._take_click(click_bounds, Some(id)) == ClickState::Clicked {
-                self.active_input = Some(ActiveInput::new(id, *num));
-                return false;
-            }
+        let mut text = num.to_string();
+        if self._text_box(
+            &mut text,
+            None,
+            |c| c.is_ascii_digit(),
+            id,
+            is_active,
+            draw_cursor,
+        ) {
+            // Typing too big a number just doesn't do anything.
+            *num = parse_num_text(&text, *num);
+            return true;
+        }
 
-            // Draw the outline
-            let mut caret = self.caret + HALF_PADDING;
-            let bg_color =
-                self.buffer[self.caret.y as usize * self.buffer_width + self.caret.x as usize];
-            shared::draw_rectangle(
-                caret,
-                bordered_size,
-                shared::COLOR_BUTTON_TEXT,
-                &mut self.buffer,
-                self.buffer_width,
-            );
-            caret += IVec2::splat(BORDER_SIZE);
-            shared::draw_rectangle(
-                caret,
-                bordered_contents_size,
-                bg_color,
-                &mut self.buffer,
-                self.buffer_width,
-            );
+        if self.button("+") {
+            *num = num.saturating_add(1);
+            return true;
+        }
 
-            // Draw the text
-            caret += OTHER_HALF_PADDING; // makes the whole thing look more centered
-            if is_active {
-                if self.window.is_key_released(Key::Backspace) {
-                    *num /= 10;
-                    return true;
-                }
+        false
+    }
 
-                let get_pressed_num = |keys: [Key; 10]| -> Option<u8> {
-                    for key in keys {
-                        if self.window.is_key_released(key) {
-                            return Some(key as u8 - keys[0] as u8);
-                        }
-                    }
-                    keys.into_iter()
-                        .find(|&key| self.window.is_key_released(key))
-                        .map(|key| key as u8 - keys[0] as u8)
-                };
-                let pressed_num =
-                    get_pressed_num(NUM_KEYS).or_else(|| get_pressed_num(NUM_PAD_KEYS));
-                if let Some(pressed_num) = pressed_num {
-                    // Do num *= 10 and num += pressed_num, but without crashing
-                    // or changing into 4294967295 when the number gets too big.
-                    if let Some(new_num) = num
-                        .checked_mul(10)
-                        .and_then(|n| n.checked_add(i32::from(pressed_num)))
-                    {
-                        *num = new_num;
+    /// Draws/handles a text input `width` chars wide that takes up to `max_len` chars, only ones
+    /// that are `allowed`. Letters are typed in uppercase. Works like [`Self::number_input`]
+    /// otherwise.
+    fn text_input(
+        &mut self,
+        text: &mut String,
+        width: usize,
+        max_len: usize,
+        allowed: fn(char) -> bool,
+    ) -> bool {
+        let id = self.take_id();
+        if let Some(inactive_input) = self.inactive_input.as_mut() {
+            if inactive_input.id == id {
+                *text = mem::take(&mut inactive_input.text);
+                self.inactive_input = None;
+                return true;
+            }
+        }
+
+        if let Some(mut active_input) = self.active_input.clone() {
+            if active_input.id == id {
+                let draw_cursor = active_input.text_cursor_blink();
+                let mutated = self._text_box(
+                    &mut active_input.text,
+                    Some(width),
+                    allowed,
+                    id,
+                    true,
+                    draw_cursor,
+                );
+                if mutated {
+                    if active_input.text.chars().count() > max_len {
+                        active_input.text.pop();
                     }
+                    self.active_input = Some(active_input);
                     return true;
                 }
-
-                // Draw the text cursor if needed
-                if draw_cursor {
-                    let last_glyph = glyphs.last().cloned().unwrap();
-                    shared::draw_rectangle(
-                        caret
-                            + IVec2 {
-                                x: last_glyph.position.x as i32,
-                                y: 0,
-                            },
-                        IVec2 {
-                            x: glyphs_size.x - last_glyph.position.x as i32,
-                            y: font.height() as i32,
-                        },
-                        shared::COLOR_TEXT_CURSOR,
-                        &mut self.buffer,
-                        self.buffer_width,
-                    );
+                return false;
+            }
+        }
+        self._text_box(text, Some(width), allowed, id, false, false)
+    }
+    fn _text_box(
+        &mut self,
+        string: &mut String,
+        width: Option<usize>,
+        allowed: fn(char) -> bool,
+        id: u16,
+        is_active: bool,
+        draw_cursor: bool,
+    ) -> bool {
+        let text = string.as_str().into();
+        let font = self.font_for(text);
+        let mut glyphs = Vec::new();
+        let mut glyphs_size = text::layout_paragraph(font, f32::INFINITY, text.str, &mut glyphs);
+        if let Some(width) = width {
+            // Don't change size while typing. If the text doesn't fit, show the end of it.
+            let width_px = (font.h_advance(font.glyph_id('0')) * width as f32).ceil() as i32;
+            let char_count = text.str.chars().count();
+            let mut shown = text.str;
+            if char_count > width {
+                shown = &shown[shown.char_indices().nth(char_count - width).unwrap().0..];
+            }
+            loop {
+                glyphs.clear();
+                glyphs_size = text::layout_paragraph(font, f32::INFINITY, shown, &mut glyphs);
+                let mut chars = shown.chars();
+                if glyphs_size.x <= width_px || chars.next().is_none() {
+                    break;
                 }
+                shown = chars.as_str();
             }
-            self._draw_glyphs_at(glyphs, font, caret, shared::COLOR_BUTTON_TEXT);
+            glyphs_size.x = width_px;
+        }
+
+        static PADDING: IVec2 = IVec2 {
+            x: BUTTON_PADDING_HORIZONTAL,
+            y: BUTTON_PADDING_VERTICAL,
+        };
+        static HALF_PADDING: IVec2 = IVec2 {
+            x: PADDING.x / 2,
+            y: PADDING.y / 2,
+        };
+        // This may be different if the padding is an odd number.
+        static OTHER_HALF_PADDING: IVec2 = IVec2 {
+            x: BUTTON_PADDING_HORIZONTAL - HALF_PADDING.x,
+            y: BUTTON_PADDING_VERTICAL - HALF_PADDING.y,
+        };
+        let bordered_contents_size = glyphs_size + PADDING;
+        let bordered_size = bordered_contents_size + IVec2::splat(BORDER_SIZE * 2);
+        let outer_size = bordered_size + PADDING;
+        self.wrap_if_needed(outer_size);
 
-            self.caret.x += outer_size.x + self.padding.x;
+        let click_bounds = (self.caret, self.caret + outer_size);
+        if !is_active && self._take_click(click_bounds, Some(id)) == ClickState::Clicked {
+            self.active_input = Some(ActiveInput::new(id, string.clone()));
+            return false;
         }
 
-        if self.button("+") {
-            *num = num.saturating_add(1);
-            return true;
+        // Draw the outline
+        let mut caret = self.caret + HALF_PADDING;
+        let bg_color =
+            self.buffer[self.caret.y as usize * self.buffer_width + self.caret.x as usize];
+        shared::draw_rectangle(
+            caret,
+            bordered_size,
+            self.theme.button_text,
+            &mut self.buffer,
+            self.buffer_width,
+        );
+        caret += IVec2::splat(BORDER_SIZE);
+        shared::draw_rectangle(
+            caret,
+            bordered_contents_size,
+            bg_color,
+            &mut self.buffer,
+            self.buffer_width,
+        );
+
+        // Draw the text
+        caret += OTHER_HALF_PADDING; // makes the whole thing look more centered
+        if is_active {
+            if self.window.is_key_released(Key::Backspace) {
+                string.pop();
+                return true;
+            }
+
+            let typed = self
+                .window
+                .get_keys_released()
+                .into_iter()
+                .filter_map(key_to_char)
+                .find(|&c| allowed(c));
+            if let Some(c) = typed {
+                string.push(c);
+                return true;
+            }
+
+            // Draw the text cursor if needed
+            if draw_cursor {
+                // Cover the last char, or be a thin line if there isn't one.
+                let cursor_x = glyphs.last().map_or(0, |g| g.position.x as i32);
+                shared::draw_rectangle(
+                    caret + IVec2 { x: cursor_x, y: 0 },
+                    IVec2 {
+                        x: (glyphs_size.x - cursor_x).max(2),
+                        y: font.height() as i32,
+                    },
+                    self.theme.text_cursor,
+                    &mut self.buffer,
+                    self.buffer_width,
+                );
+            }
         }
+        self._draw_glyphs_at(glyphs, font, caret, self.theme.button_text);
+
+        self.caret.x += outer_size.x + self.padding.x;
 
         false
     }
@@ -502,7 +843,7 @@ impl<'f> GuiState<'f> {
         shared::draw_rectangle(
             caret,
             outer_size,
-            shared::COLOR_BUTTON_BORDER,
+            self.theme.button_border,
             &mut self.buffer,
             self.buffer_width,
         );
@@ -516,14 +857,14 @@ impl<'f> GuiState<'f> {
             shared::draw_rectangle(
                 caret,
                 inner_size,
-                shared::COLOR_BUTTON_SHADE,
+                self.theme.button_shade(),
                 &mut self.buffer,
                 self.buffer_width,
             );
             shared::draw_rectangle(
                 caret + IVec2::new(1, 1),
                 inner_size - IVec2::new(2, 1),
-                shared::COLOR_BUTTON,
+                self.theme.button,
                 &mut self.buffer,
                 self.buffer_width,
             );
@@ -532,14 +873,14 @@ impl<'f> GuiState<'f> {
             shared::draw_rectangle(
                 caret,
                 inner_size,
-                shared::COLOR_BUTTON,
+                self.theme.button,
                 &mut self.buffer,
                 self.buffer_width,
             );
         }
         caret += IVec2::new(BUTTON_PADDING_HORIZONTAL, BUTTON_PADDING_VERTICAL);
 
-        self._draw_glyphs_at(glyphs, font, caret, shared::COLOR_BUTTON_TEXT);
+        self._draw_glyphs_at(glyphs, font, caret, self.theme.button_text);
 
         self.caret.x += outer_size.x + self.padding.x;
 
@@ -663,20 +1004,23 @@ impl<'a> StrInLangExt<'a> for &'a str {
     }
 }
 
-fn point_in_rect(IVec2 { x, y }: IVec2, (min, max): (IVec2, IVec2)) -> bool {
-    min.x <= x && x <= max.x && min.y <= y && y <= max.y
+/// The char a key types, if it's a letter or digit.
+fn key_to_char(key: Key) -> Option<char> {
+    if let Some(i) = NUM_KEYS.iter().chain(&NUM_PAD_KEYS).position(|&k| k == key) {
+        return Some(char::from(b'0' + (i % 10) as u8));
+    }
+    let letter = (key as u8).wrapping_sub(Key::A as u8);
+    (letter < 26).then(|| char::from(b'A' + letter))
 }
 
-trait StrHashMap {
-    /// Returns a mutable reference to the value, inserting its default value
-    /// if necessary, and cloning the key if so.
-    fn get_mut_or_create(&mut self, key: &str) -> &mut Vec<Glyph>;
-}
-impl StrHashMap for HashMap<Box<str>, Vec<Glyph>> {
-    fn get_mut_or_create(&mut self, key: &str) -> &mut Vec<Glyph> {
-        if !self.contains_key(key) {
-            self.insert(key.into(), Vec::new());
-        }
-        self.get_mut(key).unwrap()
+/// Parses what's in a number input, keeping `old_num` if it's too big.
+fn parse_num_text(text: &str, old_num: i32) -> i32 {
+    match text {
+        "" | "-" => 0,
+        _ => text.parse().unwrap_or(old_num),
     }
 }
+
+fn point_in_rect(IVec2 { x, y }: IVec2, (min, max): (IVec2, IVec2)) -> bool {
+    min.x <= x && x <= max.x && min.y <= y && y <= max.y
+}
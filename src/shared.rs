@@ -1,51 +1,66 @@
 use glam::IVec2;
+use std::{
+    env, fs, io,
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::board::{self, Board};
+use crate::board_code;
+use crate::theme::Theme;
 
 pub const DEBUG_PRINTS: bool = cfg!(debug_assertions);
 pub const DEBUG_ANTI_GUESS: bool = DEBUG_PRINTS;
+pub const DEBUG_SOLVER: bool = DEBUG_PRINTS;
 
 pub static FIRA_CODE_BYTES: &[u8] = include_bytes!("../fonts/Fira_Code/FiraCode-Regular.ttf");
 pub static NOTO_EMOJI_BYTES: &[u8] = include_bytes!("../fonts/Noto_Emoji/NotoEmoji-Regular.ttf");
 pub static NOTO_SANS_JP_BYTES: &[u8] =
     include_bytes!("../fonts/Noto_Sans_JP/NotoSansJP-Regular.ttf");
 
-pub const COLOR_OOB: u32 = 0x00000000;
-pub const COLOR_LINE: u32 = 0x00cccc00;
-pub const COLOR_UNOPENED: u32 = 0x00ffff00;
-pub const COLOR_OPENED: u32 = 0x00777700;
-
-pub const COLOR_TEXT_LIGHT: u32 = COLOR_UNOPENED;
-pub const COLOR_TEXT_DARK: u32 = COLOR_OPENED;
-pub const COLOR_TEXT_WRONG_FLAG: u32 = 0x00ff0000;
-
-pub const COLOR_MESSAGE_BOX: u32 = 0x00223377;
-pub const COLOR_MESSAGE_BORDER: u32 = 0x00ffffff;
-pub const COLOR_MESSAGE_TEXT: u32 = COLOR_MESSAGE_BORDER;
-
-pub const COLOR_BUTTON: u32 = 0x00CFD495;
-pub const COLOR_BUTTON_BORDER: u32 = COLOR_MESSAGE_BORDER;
-pub const COLOR_BUTTON_TEXT: u32 = 0x00000000;
-pub const COLOR_BUTTON_SHADE: u32 = {
-    let bytes = COLOR_BUTTON.to_be_bytes();
-    u32::from_be_bytes([
-        0, // always zero
-        bytes[1] / 2,
-        bytes[2] / 2,
-        bytes[3] / 2,
-    ])
-};
-
-pub const COLOR_TEXT_CURSOR: u32 = 0x000000ff;
-
 // In pixels
-pub const CELL_SIZE: usize = 32;
-pub const CELL_SIZE_F: f32 = 32.0;
+/// How big cells are when the board fits on screen at that size.
+pub const DEFAULT_CELL_SIZE: usize = 32;
+pub const MIN_CELL_SIZE: usize = 8;
+pub const MAX_CELL_SIZE: usize = 96;
+/// Height of the bar above the board with the counters and restart button.
+pub const STATUS_BAR_HEIGHT: usize = 40;
+
+// 4 is the minimum that doesn't crash :)
+pub const MIN_COLS: usize = 4;
+pub const MIN_ROWS: usize = 4;
+// Bigger boards than fit on screen get scrolled around.
+pub const MAX_COLS: usize = 1000;
+pub const MAX_ROWS: usize = 1000;
 
 pub struct Config {
     pub lang: Lang,
     pub cell_cols: usize,
     pub cell_rows: usize,
     pub mine_count: usize,
+    /// Only generate boards that can be cleared without guessing.
+    pub no_guess: bool,
+    /// Play on a board with no edges (see `infinite`), as dense as this one
+    /// would be.
+    pub infinite: bool,
+    /// Whether unflagging a cell leaves a question mark first.
+    pub question_marks: bool,
+    /// Makes the game reproducible. Random if `None`.
+    pub seed: Option<u64>,
+    /// A board code (see `board_code`) for the exact layout to play, if any.
+    pub code: Option<String>,
+    /// A whole board to play, opened cells and all (see `board_file`). Takes
+    /// priority over `code`.
+    pub board: Option<Board>,
+    pub theme: Theme,
+    /// Whether the numbers use the theme's colour-blind palette.
+    pub color_blind: bool,
     // These are set by the game window
+    /// Not counting the grid lines. Changes as the window is resized or
+    /// zoomed.
+    pub cell_size: usize,
+    /// The top left cell in view, for boards too big for the window.
+    pub scroll: (usize, usize),
     pub buffer_width: usize,
     pub buffer_height: usize,
 }
@@ -56,7 +71,17 @@ impl Default for Config {
             cell_cols: 10,
             cell_rows: 10,
             mine_count: 10,
+            no_guess: false,
+            infinite: false,
+            question_marks: false,
+            seed: None,
+            code: None,
+            board: None,
+            theme: Theme::default(),
+            color_blind: false,
             lang: Default::default(),
+            cell_size: DEFAULT_CELL_SIZE,
+            scroll: (0, 0),
             buffer_width: Default::default(),
             buffer_height: Default::default(),
         }
@@ -68,26 +93,158 @@ impl Config {
         self.lang.en_jp(t_en, t_jp)
     }
 
+    /// The colour for a number from 1 to 8.
+    pub fn number_color(&self, n: u8) -> u32 {
+        let palette = if self.color_blind {
+            &self.theme.color_blind_numbers
+        } else {
+            &self.theme.numbers
+        };
+        palette[usize::from(n) - 1]
+    }
+
+    /// Checks that the board this would play is one the game can handle. The
+    /// setup window keeps its settings in range, but board codes, board files
+    /// and command line flags can be anything.
+    pub fn validate(&self) -> Result<(), String> {
+        let (cols, rows) = if let Some(board) = &self.board {
+            (board.cols(), board.rows())
+        } else if let Some(code) = &self.code {
+            let board = board_code::decode(code)?;
+            (board.cols(), board.rows())
+        } else {
+            (self.cell_cols, self.cell_rows)
+        };
+        if !(MIN_COLS..=MAX_COLS).contains(&cols) || !(MIN_ROWS..=MAX_ROWS).contains(&rows) {
+            return Err(format!(
+                "the board is {cols}x{rows}, but it can only be {MIN_COLS}x{MIN_ROWS} to {MAX_COLS}x{MAX_ROWS}"
+            ));
+        }
+        // Fixed layouts bring their own mines.
+        let max_mines = cols * rows - board::SAFE_CELLS_FOR_FIRST_CLICK;
+        if self.board.is_none() && self.code.is_none() && self.mine_count > max_mines {
+            return Err(format!(
+                "{} mines don't fit on the board, which has room for {max_mines}",
+                self.mine_count
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn cell_size_f(&self) -> f32 {
+        self.cell_size as f32
+    }
+
+    /// Picks the biggest cell size (within limits) that fits the status bar
+    /// and board in `width` by `height` pixels.
+    pub fn fit_cell_size(&mut self, width: usize, height: usize) {
+        let fit_x = width.saturating_sub(1) / self.cell_cols.max(1);
+        let fit_y = height.saturating_sub(STATUS_BAR_HEIGHT + 1) / self.cell_rows.max(1);
+        // Each cell has a grid line too.
+        self.cell_size = fit_x
+            .min(fit_y)
+            .saturating_sub(1)
+            .clamp(MIN_CELL_SIZE, MAX_CELL_SIZE);
+    }
+
+    /// Sizes the buffer to fit the status bar and board.
+    pub fn fit_buffer_to_board(&mut self) {
+        self.buffer_width = self.board_width() + 1;
+        self.buffer_height = self.board_height() + 1;
+    }
+
+    /// How many columns and rows are in view, counting ones cut off at the
+    /// edge of the window.
+    pub fn view_size(&self) -> (usize, usize) {
+        let (cols, rows) = self.cells_in_window(usize::div_ceil);
+        (
+            cols.min(self.cell_cols - self.scroll.0),
+            rows.min(self.cell_rows - self.scroll.1),
+        )
+    }
+    /// How many whole columns and rows fit in the window.
+    fn whole_cells_in_window(&self) -> (usize, usize) {
+        self.cells_in_window(|pixels, cell| pixels / cell)
+    }
+    fn cells_in_window(&self, div: impl Fn(usize, usize) -> usize) -> (usize, usize) {
+        (
+            div(self.buffer_width.saturating_sub(1), self.cell_size + 1),
+            div(
+                self.buffer_height.saturating_sub(STATUS_BAR_HEIGHT + 1),
+                self.cell_size + 1,
+            ),
+        )
+    }
+    /// Whether some of the board is out of view.
+    pub fn is_scrollable(&self) -> bool {
+        let (cols, rows) = self.whole_cells_in_window();
+        cols < self.cell_cols || rows < self.cell_rows
+    }
+
+    /// Scrolls by `dx`,`dy` cells, stopping once the board's edge is in view.
+    pub fn scroll_by(&mut self, (dx, dy): (isize, isize)) {
+        self.scroll = (
+            self.scroll.0.saturating_add_signed(dx),
+            self.scroll.1.saturating_add_signed(dy),
+        );
+        self.clamp_scroll();
+    }
+    /// Scrolls as little as possible to bring the cell fully into view.
+    pub fn scroll_to(&mut self, (x, y): (usize, usize)) {
+        let (cols, rows) = self.whole_cells_in_window();
+        let fit = |scroll: usize, i: usize, count: usize| {
+            scroll.min(i).max((i + 1).saturating_sub(count.max(1)))
+        };
+        self.scroll = (fit(self.scroll.0, x, cols), fit(self.scroll.1, y, rows));
+        self.clamp_scroll();
+    }
+    /// Keeps the scroll in bounds, after scrolling or resizing.
+    pub fn clamp_scroll(&mut self) {
+        let (cols, rows) = self.whole_cells_in_window();
+        self.scroll = (
+            self.scroll.0.min(self.cell_cols.saturating_sub(cols)),
+            self.scroll.1.min(self.cell_rows.saturating_sub(rows)),
+        );
+    }
+
+    /// The whole board, in pixels.
     pub fn board_width(&self) -> usize {
-        (CELL_SIZE + 1) * self.cell_cols
+        (self.cell_size + 1) * self.cell_cols
     }
+    /// Includes the status bar above the board.
     pub fn board_height(&self) -> usize {
-        (CELL_SIZE + 1) * self.cell_rows
+        STATUS_BAR_HEIGHT + (self.cell_size + 1) * self.cell_rows
     }
 
-    #[inline]
-    pub fn cell_coords_to_idx(&self, x: usize, y: usize) -> usize {
-        y * self.cell_cols + x
+    /// How far the board is scrolled, in pixels.
+    pub fn scroll_pos(&self) -> (usize, usize) {
+        (
+            self.scroll.0 * (self.cell_size + 1),
+            self.scroll.1 * (self.cell_size + 1),
+        )
     }
 
+    /// Converts cell coords to the pixel coords of its top left corner (on
+    /// the grid lines), or `None` if it's scrolled off the top or left.
+    pub fn cell_to_pos(&self, (x, y): (usize, usize)) -> Option<(usize, usize)> {
+        Some((
+            x.checked_sub(self.scroll.0)? * (self.cell_size + 1),
+            STATUS_BAR_HEIGHT + y.checked_sub(self.scroll.1)? * (self.cell_size + 1),
+        ))
+    }
     /// Converts pixel coords to cell coords.
     pub fn pos_to_cell(&self, (x, y): (usize, usize)) -> Option<(usize, usize)> {
-        if x < self.board_width()
-            && x % (CELL_SIZE + 1) != 0
-            && y < self.board_height()
-            && y % (CELL_SIZE + 1) != 0
-        {
-            Some((x / (CELL_SIZE + 1), y / (CELL_SIZE + 1)))
+        if y < STATUS_BAR_HEIGHT {
+            return None;
+        }
+        let (scroll_x, scroll_y) = self.scroll_pos();
+        let (x, y) = (x + scroll_x, y + scroll_y);
+        if x >= self.board_width() || y >= self.board_height() {
+            return None;
+        }
+        let y = y - STATUS_BAR_HEIGHT;
+        if !x.is_multiple_of(self.cell_size + 1) && !y.is_multiple_of(self.cell_size + 1) {
+            Some((x / (self.cell_size + 1), y / (self.cell_size + 1)))
         } else {
             None
         }
@@ -97,6 +254,171 @@ impl Config {
         self.pos_to_cell((x as usize, y as usize))
     }
 }
+#[test]
+fn test_validate() {
+    assert!(Config::default().validate().is_ok());
+    for (cell_cols, cell_rows, mine_count) in [(0, 10, 0), (10, 3, 0), (1001, 10, 0), (4, 4, 8)] {
+        let cfg = Config {
+            cell_cols,
+            cell_rows,
+            mine_count,
+            ..Config::default()
+        };
+        assert!(cfg.validate().is_err());
+    }
+    // The layout's size is what matters, not the settings'.
+    let small = Board::with_mines(2, 2, vec![false; 4].into_boxed_slice());
+    let code = Config {
+        cell_rows: 0,
+        code: Some(board_code::encode(&small)),
+        ..Config::default()
+    };
+    assert!(code.validate().is_err());
+    let file = Config {
+        board: Some(Board::with_mines(4, 4, vec![true; 16].into_boxed_slice())),
+        ..Config::default()
+    };
+    assert!(file.validate().is_ok());
+}
+
+#[test]
+fn test_pos_to_cell() {
+    let mut cfg = Config::default();
+    let (x, y) = cfg.cell_to_pos((3, 2)).unwrap();
+    assert_eq!(cfg.pos_to_cell((x + 1, y + 1)), Some((3, 2)));
+    assert_eq!(
+        cfg.pos_to_cell((x + cfg.cell_size, y + cfg.cell_size)),
+        Some((3, 2))
+    );
+    // Grid lines and the status bar aren't cells.
+    assert_eq!(cfg.pos_to_cell((x, y + 1)), None);
+    assert_eq!(cfg.pos_to_cell((x + 1, y)), None);
+    assert_eq!(cfg.pos_to_cell((x + 1, STATUS_BAR_HEIGHT / 2)), None);
+    assert_eq!(cfg.pos_to_cell((x + 1, cfg.board_height())), None);
+
+    // A window 4 cells across scrolls over the 10 columns, but no further
+    // than the last one.
+    cfg.buffer_width = (cfg.cell_size + 1) * 4 + 1;
+    cfg.buffer_height = cfg.board_height() + 1;
+    assert!(cfg.is_scrollable());
+    cfg.scroll_by((2, 0));
+    assert_eq!(cfg.cell_to_pos((1, 2)), None);
+    assert_eq!(cfg.pos_to_cell((x + 1, y + 1)), Some((5, 2)));
+    cfg.scroll_by((100, 100));
+    assert_eq!(cfg.scroll, (6, 0));
+    assert_eq!(cfg.view_size(), (4, 10));
+    cfg.scroll_to((1, 9));
+    assert_eq!(cfg.scroll, (1, 0));
+
+    // A 10x10 board in 10 times the default size (plus grid lines) is back at
+    // the default size.
+    let mut cfg = Config::default();
+    cfg.fit_cell_size(
+        (DEFAULT_CELL_SIZE + 1) * 10 + 1,
+        STATUS_BAR_HEIGHT + (DEFAULT_CELL_SIZE + 1) * 10 + 1,
+    );
+    assert_eq!(cfg.cell_size, DEFAULT_CELL_SIZE);
+    cfg.fit_cell_size(200, 10_000);
+    assert_eq!(cfg.cell_size, 18);
+    assert_eq!(
+        cfg.pos_to_cell((19 * 9 + 1, STATUS_BAR_HEIGHT + 1)),
+        Some((9, 0))
+    );
+    cfg.fit_cell_size(0, 0);
+    assert_eq!(cfg.cell_size, MIN_CELL_SIZE);
+}
+
+/// Where saves and such are kept. It might not exist yet.
+pub fn data_dir() -> Option<PathBuf> {
+    let base = if cfg!(target_os = "windows") {
+        PathBuf::from(env::var_os("APPDATA")?)
+    } else if cfg!(target_os = "macos") {
+        PathBuf::from(env::var_os("HOME")?).join("Library/Application Support")
+    } else if let Some(xdg_data_home) = env::var_os("XDG_DATA_HOME") {
+        PathBuf::from(xdg_data_home)
+    } else {
+        PathBuf::from(env::var_os("HOME")?).join(".local/share")
+    };
+    Some(base.join("minesweeper"))
+}
+
+/// Reads a file in `data_dir`, if it exists.
+pub fn read_data_file(name: &str) -> io::Result<Option<String>> {
+    let path = data_dir().ok_or_else(no_data_dir)?.join(name);
+    match fs::read_to_string(path) {
+        Ok(s) => Ok(Some(s)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Writes a file in `data_dir`, creating the directory if needed.
+pub fn write_data_file(name: &str, contents: &str) -> io::Result<()> {
+    let dir = data_dir().ok_or_else(no_data_dir)?;
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(name), contents)
+}
+
+/// Writes a file in `data_dir` named after the current time, like
+/// `prefix-1234567890.extension`. Returns where it went.
+pub fn write_new_data_file(prefix: &str, extension: &str, contents: &str) -> io::Result<PathBuf> {
+    let secs = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let name = format!("{prefix}-{secs}.{extension}");
+    write_data_file(&name, contents)?;
+    Ok(data_dir().ok_or_else(no_data_dir)?.join(name))
+}
+
+/// Deletes a file in `data_dir`, if it exists.
+pub fn delete_data_file(name: &str) -> io::Result<()> {
+    let path = data_dir().ok_or_else(no_data_dir)?.join(name);
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+fn no_data_dir() -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, "couldn't find a place to save")
+}
+
+/// Measures play time, which can be paused and resumed.
+#[derive(Copy, Clone, Default)]
+pub struct Stopwatch {
+    /// Time from before the latest start.
+    elapsed: Duration,
+    running_since: Option<Instant>,
+}
+
+impl Stopwatch {
+    /// A stopped stopwatch that already has some time on it.
+    pub fn with_elapsed(elapsed: Duration) -> Self {
+        Self {
+            elapsed,
+            running_since: None,
+        }
+    }
+
+    pub fn start(&mut self) {
+        if self.running_since.is_none() {
+            self.running_since = Some(Instant::now());
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.elapsed = self.elapsed();
+        self.running_since = None;
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+            + self
+                .running_since
+                .map_or(Duration::ZERO, |since| since.elapsed())
+    }
+}
 
 #[derive(Copy, Clone, Default, PartialEq, Eq)]
 pub enum Lang {
@@ -154,7 +476,9 @@ fn test_lerp_colors() {
 fn lerp_u8(min: u8, max: u8, amt: f32) -> u8 {
     let min = f32::from(min);
     let max = f32::from(max);
-    (min + ((max - min) * amt)) as u8
+    // Truncate the difference rather than the result, so that it rounds
+    // towards `min` in either direction.
+    (min + ((max - min) * amt).trunc()) as u8
 }
 #[test]
 fn test_lerp_u8() {
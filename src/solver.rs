@@ -0,0 +1,559 @@
+//! Works out which cells are provably safe or provably mines, using only what
+//! the player can see: the opened numbers, the flags, and the mine count.
+
+use std::collections::HashMap;
+
+use crate::board::{self, Board, Cell};
+
+/// Give up on exhaustively enumerating a frontier component after visiting
+/// this many partial solutions. Huge, tangled frontiers are rare, and the
+/// simpler rules usually still make progress on them.
+const MAX_ENUMERATION_STEPS: usize = 1 << 20;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Rule {
+    /// A single number has no room left for either mines or safe cells.
+    Single,
+    /// One number's unopened cells are a subset of another's.
+    Subset,
+    /// Every way of placing mines that agrees with all the numbers and the
+    /// mine count.
+    Full,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Deduction {
+    pub x: usize,
+    pub y: usize,
+    pub is_mine: bool,
+    pub rule: Rule,
+}
+
+/// Returns every cell that can be proven to be safe or a mine. Flags are
+/// trusted to be correct.
+pub fn solve(board: &Board) -> Vec<Deduction> {
+    let mut solver = Solver::new(board);
+    while solver.apply_single() || solver.apply_subset() {}
+    solver.apply_full();
+    solver.deductions
+}
+
+/// "The unopened `cells` contain exactly `mines` mines." Cells are board indices.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct Constraint {
+    pub cells: Vec<usize>,
+    pub mines: usize,
+}
+
+/// A group of frontier cells that share constraints, and so have to be
+/// enumerated together.
+pub(crate) struct Component {
+    pub cells: Vec<usize>,
+    /// Whether every solution was counted. If not, the counts are empty.
+    pub complete: bool,
+    /// `solutions[k]` is the number of ways to place exactly `k` mines in `cells`.
+    pub solutions: Vec<f64>,
+    /// `mine_solutions[k][i]` is how many of those have a mine on `cells[i]`.
+    pub mine_solutions: Vec<Vec<f64>>,
+}
+
+impl Component {
+    /// Which mine totals this component can have. Incomplete components
+    /// might have any.
+    pub fn possible_totals(&self) -> Vec<bool> {
+        if !self.complete {
+            return vec![true; self.cells.len() + 1];
+        }
+        self.solutions.iter().map(|&n| n > 0.0).collect()
+    }
+}
+
+pub(crate) struct Enumeration {
+    pub components: Vec<Component>,
+    /// Unknown cells that aren't next to any number.
+    pub interior: Vec<usize>,
+    /// Mines not yet flagged or deduced, if the flags don't outnumber the mines.
+    pub mines_left: Option<usize>,
+}
+
+impl Enumeration {
+    /// Whether the frontier having `frontier_mines` mines leaves a valid
+    /// number of mines for the interior.
+    pub fn is_feasible_total(&self, frontier_mines: usize) -> bool {
+        match self.mines_left {
+            Some(mines_left) => {
+                frontier_mines <= mines_left && mines_left - frontier_mines <= self.interior.len()
+            }
+            None => true,
+        }
+    }
+
+    /// Which mine totals all the components except `skip` can add up to.
+    pub fn possible_totals_except(&self, skip: Option<usize>) -> Vec<bool> {
+        let mut totals = vec![true];
+        for (i, component) in self.components.iter().enumerate() {
+            if Some(i) != skip {
+                totals = add_totals(&totals, &component.possible_totals());
+            }
+        }
+        totals
+    }
+}
+
+/// Every sum of a total from `a` and a total from `b`.
+fn add_totals(a: &[bool], b: &[bool]) -> Vec<bool> {
+    let mut sums = vec![false; a.len() + b.len() - 1];
+    for (i, _) in a.iter().enumerate().filter(|(_, &p)| p) {
+        for (j, _) in b.iter().enumerate().filter(|(_, &p)| p) {
+            sums[i + j] = true;
+        }
+    }
+    sums
+}
+
+pub(crate) struct Solver {
+    cols: usize,
+    rows: usize,
+    /// For each cell: `Some(true)` for known mines (including flags),
+    /// `Some(false)` for known safe cells, `None` for unknown.
+    known: Vec<Option<bool>>,
+    /// The number shown on each opened cell.
+    numbers: Vec<Option<u8>>,
+    mine_count: usize,
+    pub deductions: Vec<Deduction>,
+}
+
+impl Solver {
+    pub fn new(board: &Board) -> Self {
+        let (cols, rows) = (board.cols(), board.rows());
+        let mut known = Vec::with_capacity(cols * rows);
+        let mut numbers = Vec::with_capacity(cols * rows);
+        for row in board.cells() {
+            for &cell in row {
+                known.push(match cell {
+                    Cell::Unopened | Cell::Questioned => None,
+                    Cell::Opened => Some(false),
+                    Cell::Flagged => Some(true),
+                });
+            }
+        }
+        for y in 0..rows {
+            for x in 0..cols {
+                numbers.push((board.cell(x, y) == Cell::Opened).then(|| board.nearby_mines(x, y)));
+            }
+        }
+        Self {
+            cols,
+            rows,
+            known,
+            numbers,
+            mine_count: board.mine_count(),
+            deductions: Vec::new(),
+        }
+    }
+
+    fn deduce(&mut self, i: usize, is_mine: bool, rule: Rule) {
+        if self.known[i].is_some() {
+            return;
+        }
+        self.known[i] = Some(is_mine);
+        self.deductions.push(Deduction {
+            x: i % self.cols,
+            y: i / self.cols,
+            is_mine,
+            rule,
+        });
+    }
+
+    /// One constraint for each opened number that still touches unknown cells.
+    pub fn constraints(&self) -> Vec<Constraint> {
+        let mut constraints = Vec::new();
+        for (i, number) in self.numbers.iter().enumerate() {
+            let Some(number) = *number else {
+                continue;
+            };
+            let mut cells = Vec::new();
+            let mut known_mines = 0;
+            board::do_surrounding(
+                self.cols,
+                self.rows,
+                i % self.cols,
+                i / self.cols,
+                |sx, sy| {
+                    let si = sy * self.cols + sx;
+                    match self.known[si] {
+                        None => cells.push(si),
+                        Some(true) => known_mines += 1,
+                        Some(false) => {}
+                    }
+                },
+            );
+            // Too many flags around it; the player has made a mistake.
+            let Some(mines) = usize::from(number).checked_sub(known_mines) else {
+                continue;
+            };
+            if !cells.is_empty() && mines <= cells.len() {
+                cells.sort_unstable();
+                constraints.push(Constraint { cells, mines });
+            }
+        }
+        constraints.sort_unstable();
+        constraints.dedup();
+        constraints
+    }
+
+    pub fn apply_single(&mut self) -> bool {
+        let before = self.deductions.len();
+        for constraint in self.constraints() {
+            if constraint.mines == 0 || constraint.mines == constraint.cells.len() {
+                let is_mine = constraint.mines > 0;
+                for i in constraint.cells {
+                    self.deduce(i, is_mine, Rule::Single);
+                }
+            }
+        }
+        self.deductions.len() > before
+    }
+
+    pub fn apply_subset(&mut self) -> bool {
+        let before = self.deductions.len();
+        let constraints = self.constraints();
+        let mut by_cell: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (ci, constraint) in constraints.iter().enumerate() {
+            for &i in &constraint.cells {
+                by_cell.entry(i).or_default().push(ci);
+            }
+        }
+        for small in &constraints {
+            for &ci in &by_cell[&small.cells[0]] {
+                let big = &constraints[ci];
+                if big.cells.len() <= small.cells.len()
+                    || big.mines < small.mines
+                    || !small
+                        .cells
+                        .iter()
+                        .all(|i| big.cells.binary_search(i).is_ok())
+                {
+                    continue;
+                }
+                let rest: Vec<usize> = big
+                    .cells
+                    .iter()
+                    .copied()
+                    .filter(|i| small.cells.binary_search(i).is_err())
+                    .collect();
+                let rest_mines = big.mines - small.mines;
+                if rest_mines == 0 || rest_mines == rest.len() {
+                    for i in rest {
+                        self.deduce(i, rest_mines > 0, Rule::Subset);
+                    }
+                }
+            }
+        }
+        self.deductions.len() > before
+    }
+
+    pub fn apply_full(&mut self) -> bool {
+        let before = self.deductions.len();
+        let Some(enumeration) = self.enumerate() else {
+            return false;
+        };
+
+        for (ci, component) in enumeration.components.iter().enumerate() {
+            if !component.complete {
+                continue;
+            }
+            let others = enumeration.possible_totals_except(Some(ci));
+            let feasible = |k: usize| {
+                others
+                    .iter()
+                    .enumerate()
+                    .any(|(t, &p)| p && enumeration.is_feasible_total(k + t))
+            };
+            for (j, &i) in component.cells.iter().enumerate() {
+                let mut can_be_mine = false;
+                let mut can_be_safe = false;
+                for (k, &count) in component.solutions.iter().enumerate() {
+                    let mines = component.mine_solutions[k][j];
+                    if count > 0.0 && feasible(k) {
+                        can_be_mine |= mines > 0.0;
+                        can_be_safe |= count - mines > 0.0;
+                    }
+                }
+                if can_be_mine != can_be_safe {
+                    self.deduce(i, can_be_mine, Rule::Full);
+                }
+            }
+        }
+
+        if let Some(mines_left) = enumeration.mines_left {
+            let interior_len = enumeration.interior.len();
+            let mut can_be_mine = false;
+            let mut can_be_safe = false;
+            for (t, &p) in enumeration.possible_totals_except(None).iter().enumerate() {
+                if p && enumeration.is_feasible_total(t) {
+                    can_be_mine |= mines_left - t >= 1;
+                    can_be_safe |= mines_left - t < interior_len;
+                }
+            }
+            if can_be_mine != can_be_safe {
+                for &i in &enumeration.interior {
+                    self.deduce(i, can_be_mine, Rule::Full);
+                }
+            }
+        }
+
+        self.deductions.len() > before
+    }
+
+    /// Enumerates every way of placing mines around the numbers, one
+    /// independent component at a time. Returns `None` if the numbers
+    /// contradict each other (i.e., there's a wrong flag).
+    pub fn enumerate(&self) -> Option<Enumeration> {
+        let constraints = self.constraints();
+
+        // Group cells into components that share constraints.
+        let mut component_of: HashMap<usize, usize> = HashMap::new();
+        let mut component_constraints: Vec<Vec<&Constraint>> = Vec::new();
+        for constraint in &constraints {
+            let mut touching: Vec<usize> = constraint
+                .cells
+                .iter()
+                .filter_map(|i| component_of.get(i).copied())
+                .collect();
+            touching.sort_unstable();
+            touching.dedup();
+            let target = match touching.first() {
+                Some(&target) => target,
+                None => {
+                    component_constraints.push(Vec::new());
+                    component_constraints.len() - 1
+                }
+            };
+            for &other in touching.iter().skip(1) {
+                let moved = std::mem::take(&mut component_constraints[other]);
+                for c in &moved {
+                    for &i in &c.cells {
+                        component_of.insert(i, target);
+                    }
+                }
+                component_constraints[target].extend(moved);
+            }
+            for &i in &constraint.cells {
+                component_of.insert(i, target);
+            }
+            component_constraints[target].push(constraint);
+        }
+
+        let mut components = Vec::new();
+        for group in component_constraints.into_iter().filter(|g| !g.is_empty()) {
+            let mut cells: Vec<usize> =
+                group.iter().flat_map(|c| c.cells.iter().copied()).collect();
+            cells.sort_unstable();
+            cells.dedup();
+            components.push(enumerate_component(cells, &group)?);
+        }
+
+        let interior = (0..self.known.len())
+            .filter(|&i| self.known[i].is_none() && !component_of.contains_key(&i))
+            .collect();
+        let known_mines = self.known.iter().filter(|&&k| k == Some(true)).count();
+        Some(Enumeration {
+            components,
+            interior,
+            mines_left: self.mine_count.checked_sub(known_mines),
+        })
+    }
+}
+
+fn enumerate_component(cells: Vec<usize>, constraints: &[&Constraint]) -> Option<Component> {
+    // Visit cells in constraint order, so that constraints fill up (and
+    // prune) as early as possible.
+    let mut order: Vec<usize> = Vec::with_capacity(cells.len());
+    for constraint in constraints {
+        for &i in &constraint.cells {
+            if !order.contains(&i) {
+                order.push(i);
+            }
+        }
+    }
+    debug_assert_eq!(order.len(), cells.len());
+    let local = |i: usize| order.iter().position(|&o| o == i).unwrap();
+
+    let mut enumerator = Enumerator {
+        cell_constraints: vec![Vec::new(); order.len()],
+        needed: constraints.iter().map(|c| c.mines).collect(),
+        placed: vec![0; constraints.len()],
+        unassigned: constraints.iter().map(|c| c.cells.len()).collect(),
+        assignment: vec![false; order.len()],
+        solutions: vec![0.0; order.len() + 1],
+        mine_solutions: vec![vec![0.0; order.len()]; order.len() + 1],
+        steps: 0,
+    };
+    for (ci, constraint) in constraints.iter().enumerate() {
+        for &i in &constraint.cells {
+            enumerator.cell_constraints[local(i)].push(ci);
+        }
+    }
+    if !enumerator.run(0, 0) {
+        return Some(Component {
+            cells: order,
+            complete: false,
+            solutions: Vec::new(),
+            mine_solutions: Vec::new(),
+        });
+    }
+    if enumerator.solutions.iter().all(|&n| n == 0.0) {
+        return None;
+    }
+
+    Some(Component {
+        cells: order,
+        complete: true,
+        solutions: enumerator.solutions,
+        mine_solutions: enumerator.mine_solutions,
+    })
+}
+
+struct Enumerator {
+    /// Which constraints each (local) cell is part of.
+    cell_constraints: Vec<Vec<usize>>,
+    needed: Vec<usize>,
+    placed: Vec<usize>,
+    unassigned: Vec<usize>,
+    assignment: Vec<bool>,
+    solutions: Vec<f64>,
+    mine_solutions: Vec<Vec<f64>>,
+    steps: usize,
+}
+
+impl Enumerator {
+    /// Returns false if it ran out of steps.
+    fn run(&mut self, cell: usize, mines: usize) -> bool {
+        self.steps += 1;
+        if self.steps > MAX_ENUMERATION_STEPS {
+            return false;
+        }
+        if cell == self.assignment.len() {
+            self.solutions[mines] += 1.0;
+            for (i, &is_mine) in self.assignment.iter().enumerate() {
+                if is_mine {
+                    self.mine_solutions[mines][i] += 1.0;
+                }
+            }
+            return true;
+        }
+
+        for is_mine in [false, true] {
+            let fits = self.cell_constraints[cell].iter().all(|&ci| {
+                let placed = self.placed[ci] + usize::from(is_mine);
+                let unassigned = self.unassigned[ci] - 1;
+                placed <= self.needed[ci] && placed + unassigned >= self.needed[ci]
+            });
+            if !fits {
+                continue;
+            }
+            for &ci in &self.cell_constraints[cell] {
+                self.placed[ci] += usize::from(is_mine);
+                self.unassigned[ci] -= 1;
+            }
+            self.assignment[cell] = is_mine;
+            let finished = self.run(cell + 1, mines + usize::from(is_mine));
+            for &ci in &self.cell_constraints[cell] {
+                self.placed[ci] -= usize::from(is_mine);
+                self.unassigned[ci] += 1;
+            }
+            if !finished {
+                return false;
+            }
+        }
+        self.assignment[cell] = false;
+        true
+    }
+}
+
+#[cfg(test)]
+fn open_all(board: &mut Board, cells: &[(usize, usize)]) {
+    for &(x, y) in cells {
+        assert!(board.open(x, y));
+    }
+}
+
+#[test]
+fn test_subset_one_two_one() {
+    // ? ? ?   (mines on both ends)
+    // 1 2 1
+    let mines = vec![true, false, true, false, false, false].into_boxed_slice();
+    let mut board = Board::with_mines(3, 2, mines);
+    open_all(&mut board, &[(0, 1), (1, 1), (2, 1)]);
+    let mut deductions = solve(&board);
+    deductions.sort_by_key(|d| d.x);
+    assert_eq!(
+        deductions,
+        [
+            Deduction {
+                x: 0,
+                y: 0,
+                is_mine: true,
+                rule: Rule::Subset
+            },
+            Deduction {
+                x: 1,
+                y: 0,
+                is_mine: false,
+                rule: Rule::Single
+            },
+            Deduction {
+                x: 2,
+                y: 0,
+                is_mine: true,
+                rule: Rule::Subset
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_mine_count_clears_interior() {
+    // ? ? ? 1 0 0
+    let mines = vec![false, false, true, false, false, false].into_boxed_slice();
+    let mut board = Board::with_mines(6, 1, mines);
+    open_all(&mut board, &[(5, 0)]);
+    let deductions = solve(&board);
+    assert_eq!(deductions.len(), 3);
+    assert!(deductions.contains(&Deduction {
+        x: 2,
+        y: 0,
+        is_mine: true,
+        rule: Rule::Single
+    }));
+    assert!(deductions.contains(&Deduction {
+        x: 0,
+        y: 0,
+        is_mine: false,
+        rule: Rule::Full
+    }));
+    assert!(deductions.contains(&Deduction {
+        x: 1,
+        y: 0,
+        is_mine: false,
+        rule: Rule::Full
+    }));
+}
+
+#[test]
+fn test_deductions_are_correct() {
+    let mut rng = fastrand::Rng::with_seed(1);
+    for _ in 0..200 {
+        let mines: Box<[bool]> = (0..36).map(|_| rng.u8(0..5) == 0).collect();
+        let mut board = Board::with_mines(6, 6, mines);
+        let (x, y) = (rng.usize(0..6), rng.usize(0..6));
+        if board.is_mine(x, y) {
+            continue;
+        }
+        board.open(x, y);
+        for d in solve(&board) {
+            assert_eq!(board.is_mine(d.x, d.y), d.is_mine, "{d:?}");
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Lifetime statistics, kept for each board size and mine count.
+//!
+//! The file is plain text: a header line, then one line for each kind of
+//! board, as `cols rows mines won lost streak best_streak win_time_ms`.
+
+use std::{collections::BTreeMap, io, time::Duration};
+
+use crate::records::BoardKey;
+use crate::shared::{self, Lang};
+
+const FILE_NAME: &str = "stats.txt";
+const HEADER: &str = "minesweeper stats 1";
+
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct BoardStats {
+    pub won: u64,
+    pub lost: u64,
+    /// Wins in a row, up to now.
+    pub streak: u64,
+    pub best_streak: u64,
+    /// All the winning times added up.
+    pub win_time: Duration,
+}
+
+impl BoardStats {
+    pub fn played(&self) -> u64 {
+        self.won + self.lost
+    }
+
+    pub fn win_rate(&self) -> Option<f64> {
+        (self.played() > 0).then(|| self.won as f64 / self.played() as f64)
+    }
+
+    pub fn average_time(&self) -> Option<Duration> {
+        (self.won > 0).then(|| self.win_time / self.won as u32)
+    }
+}
+
+#[derive(Default)]
+pub struct Stats {
+    boards: BTreeMap<BoardKey, BoardStats>,
+}
+
+impl Stats {
+    /// Loads the stats, or starts over if they can't be read.
+    pub fn load() -> Self {
+        shared::read_data_file(FILE_NAME)
+            .and_then(|s| s.map_or_else(|| Ok(Self::default()), |s| Self::parse(&s)))
+            .unwrap_or_else(|err| {
+                eprintln!("Couldn't read the stats: {err}");
+                Self::default()
+            })
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        shared::write_data_file(FILE_NAME, &self.serialize())
+    }
+
+    /// Forgets everything.
+    pub fn reset() -> io::Result<()> {
+        shared::delete_data_file(FILE_NAME)
+    }
+
+    pub fn get(&self, key: BoardKey) -> BoardStats {
+        self.boards.get(&key).copied().unwrap_or_default()
+    }
+
+    pub fn add_win(&mut self, key: BoardKey, time: Duration) {
+        let stats = self.boards.entry(key).or_default();
+        stats.won += 1;
+        stats.streak += 1;
+        stats.best_streak = stats.best_streak.max(stats.streak);
+        stats.win_time += time;
+    }
+
+    pub fn add_loss(&mut self, key: BoardKey) {
+        let stats = self.boards.entry(key).or_default();
+        stats.lost += 1;
+        stats.streak = 0;
+    }
+
+    fn serialize(&self) -> String {
+        let mut s = format!("{HEADER}\n");
+        for (key, stats) in &self.boards {
+            s += &format!(
+                "{} {} {} {} {} {} {} {}\n",
+                key.cols,
+                key.rows,
+                key.mines,
+                stats.won,
+                stats.lost,
+                stats.streak,
+                stats.best_streak,
+                stats.win_time.as_millis()
+            );
+        }
+        s
+    }
+
+    fn parse(s: &str) -> io::Result<Self> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+        let mut lines = s.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(invalid("not a stats file".to_owned()));
+        }
+        let mut stats = Self::default();
+        for line in lines.filter(|line| !line.is_empty()) {
+            let nums: Vec<u64> = line
+                .split(' ')
+                .map(|part| part.parse())
+                .collect::<Result<_, _>>()
+                .map_err(|_| invalid(format!("bad line '{line}'")))?;
+            let &[cols, rows, mines, won, lost, streak, best_streak, win_time_ms] = &nums[..]
+            else {
+                return Err(invalid(format!("bad line '{line}'")));
+            };
+            let key = BoardKey {
+                cols: cols as usize,
+                rows: rows as usize,
+                mines: mines as usize,
+            };
+            stats.boards.insert(
+                key,
+                BoardStats {
+                    won,
+                    lost,
+                    streak,
+                    best_streak,
+                    win_time: Duration::from_millis(win_time_ms),
+                },
+            );
+        }
+        Ok(stats)
+    }
+}
+
+/// Describes the saved stats for a kind of board, one per line, under a heading.
+pub fn describe(key: BoardKey, lang: Lang) -> String {
+    let stats = Stats::load().get(key);
+    let percent = stats
+        .win_rate()
+        .map_or("-".to_owned(), |rate| format!("{:.0}%", rate * 100.0));
+    let average = stats
+        .average_time()
+        .map_or("-".to_owned(), |time| format!("{:.2}", time.as_secs_f64()));
+    match lang {
+        Lang::En => format!(
+            "Statistics: {}x{}, {} mines\n\
+             Played: {}\n\
+             Won: {}\n\
+             Lost: {}\n\
+             Win rate: {percent}\n\
+             Win streak: {}\n\
+             Best streak: {}\n\
+             Average time: {average}s",
+            key.cols,
+            key.rows,
+            key.mines,
+            stats.played(),
+            stats.won,
+            stats.lost,
+            stats.streak,
+            stats.best_streak,
+        ),
+        Lang::Jp => format!(
+            "統計：{}×{}、地雷{}個\n\
+             プレイ回数：{}\n\
+             勝ち：{}\n\
+             負け：{}\n\
+             勝率：{percent}\n\
+             連勝：{}\n\
+             最高連勝：{}\n\
+             平均タイム：{average}秒",
+            key.cols,
+            key.rows,
+            key.mines,
+            stats.played(),
+            stats.won,
+            stats.lost,
+            stats.streak,
+            stats.best_streak,
+        ),
+    }
+}
+
+#[test]
+fn test_stats() {
+    let key = BoardKey {
+        cols: 9,
+        rows: 9,
+        mines: 10,
+    };
+    let mut stats = Stats::default();
+    stats.add_win(key, Duration::from_secs(10));
+    stats.add_win(key, Duration::from_secs(20));
+    stats.add_loss(key);
+    stats.add_win(key, Duration::from_secs(30));
+
+    let board = stats.get(key);
+    assert_eq!(board.played(), 4);
+    assert_eq!(board.win_rate(), Some(0.75));
+    assert_eq!(board.streak, 1);
+    assert_eq!(board.best_streak, 2);
+    assert_eq!(board.average_time(), Some(Duration::from_secs(20)));
+    assert_eq!(stats.get(BoardKey { mines: 11, ..key }).win_rate(), None);
+
+    let loaded = Stats::parse(&stats.serialize()).unwrap();
+    assert_eq!(loaded.get(key), board);
+}
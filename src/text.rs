@@ -26,8 +26,13 @@ pub fn draw_glyphs<F, FS, G>(
             outlined.draw(|x, y, c| {
                 let x = x as i32 + offset_x;
                 let y = y as i32 + offset_y;
+                if x < 0 || y < 0 || x as usize >= buffer_width {
+                    return;
+                }
                 let i = y as usize * buffer_width + x as usize;
-                buffer[i] = lerp_colors(buffer[i], color, c);
+                if let Some(px) = buffer.get_mut(i) {
+                    *px = lerp_colors(*px, color, c);
+                }
             });
         }
     }
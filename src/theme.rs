@@ -0,0 +1,318 @@
+//! The colours both windows are drawn with.
+//!
+//! A theme file is plain text: a header line, then `name = value` lines.
+//! `base` picks a built-in theme to start from (`light` if there isn't one),
+//! and every other name is a colour to change, as hex `rrggbb`. Colours that
+//! aren't given come from the base. The numbers' colours are `number_1` to
+//! `number_8`, and `color_blind_number_1` to `color_blind_number_8`.
+
+use std::io;
+
+use crate::shared;
+
+const FILE_NAME: &str = "theme.txt";
+const HEADER: &str = "minesweeper theme 1";
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Theme {
+    /// Outside the board.
+    pub oob: u32,
+    pub line: u32,
+    pub unopened: u32,
+    pub opened: u32,
+
+    /// For things drawn on opened cells.
+    pub text_light: u32,
+    /// For the numbers 1 to 8.
+    pub numbers: [u32; 8],
+    /// For the numbers, in colours that stay apart for colour-blind players.
+    pub color_blind_numbers: [u32; 8],
+    /// For things drawn on unopened cells.
+    pub text_dark: u32,
+    pub text_wrong_flag: u32,
+
+    pub message_box: u32,
+    pub message_border: u32,
+    pub message_text: u32,
+
+    pub button: u32,
+    pub button_border: u32,
+    pub button_text: u32,
+    pub text_cursor: u32,
+
+    pub odds_safe: u32,
+    pub odds_mine: u32,
+    pub hint: u32,
+    pub cursor: u32,
+
+    pub status_bar: u32,
+    pub counter: u32,
+    pub counter_text: u32,
+}
+
+impl Theme {
+    /// The original yellow and blue look.
+    pub const LIGHT: Self = Self {
+        oob: 0x00000000,
+        line: 0x00cccc00,
+        unopened: 0x00ffff00,
+        opened: 0x00777700,
+        text_light: 0x00ffff00,
+        numbers: [
+            0x0080c0ff, 0x0080ff80, 0x00ff8080, 0x00c080ff, 0x00ffb060, 0x0080ffff, 0x00ffffff,
+            0x00c0c0c0,
+        ],
+        color_blind_numbers: [
+            0x0056b4e9, 0x0030d0a0, 0x00ff8030, 0x00e090c0, 0x00f0e442, 0x00e69f00, 0x00ffffff,
+            0x00a0a0a0,
+        ],
+        text_dark: 0x00777700,
+        text_wrong_flag: 0x00ff0000,
+        message_box: 0x00223377,
+        message_border: 0x00ffffff,
+        message_text: 0x00ffffff,
+        button: 0x00cfd495,
+        button_border: 0x00ffffff,
+        button_text: 0x00000000,
+        text_cursor: 0x000000ff,
+        odds_safe: 0x0000c000,
+        odds_mine: 0x00ff0000,
+        hint: 0x000080ff,
+        cursor: 0x00ff8000,
+        status_bar: 0x00cccc00,
+        counter: 0x00000000,
+        counter_text: 0x00ff0000,
+    };
+
+    pub const DARK: Self = Self {
+        oob: 0x00000000,
+        line: 0x003a3a3a,
+        unopened: 0x006a6a78,
+        opened: 0x00202024,
+        text_light: 0x00d0d0d8,
+        numbers: [
+            0x0080c0ff, 0x0080ff80, 0x00ff8080, 0x00c080ff, 0x00ffb060, 0x0080ffff, 0x00ffffff,
+            0x00c0c0c0,
+        ],
+        color_blind_numbers: [
+            0x0056b4e9, 0x0030d0a0, 0x00ff8030, 0x00e090c0, 0x00f0e442, 0x00e69f00, 0x00ffffff,
+            0x00a0a0a0,
+        ],
+        text_dark: 0x00101014,
+        text_wrong_flag: 0x00ff4040,
+        message_box: 0x00101828,
+        message_border: 0x00a0a0b0,
+        message_text: 0x00e0e0e0,
+        button: 0x00505060,
+        button_border: 0x00a0a0b0,
+        button_text: 0x00e8e8e8,
+        text_cursor: 0x0080a0ff,
+        odds_safe: 0x0000c000,
+        odds_mine: 0x00ff3030,
+        hint: 0x003090ff,
+        cursor: 0x00ff9020,
+        status_bar: 0x00303036,
+        counter: 0x00000000,
+        counter_text: 0x00ff2020,
+    };
+
+    /// Greys, like the Minesweeper that came with Windows.
+    pub const CLASSIC: Self = Self {
+        oob: 0x00000000,
+        line: 0x00808080,
+        unopened: 0x00b0b0b0,
+        opened: 0x00dcdcdc,
+        text_light: 0x00000080,
+        numbers: [
+            0x000000ff, 0x00008000, 0x00ff0000, 0x00000080, 0x00800000, 0x00008080, 0x00000000,
+            0x00808080,
+        ],
+        color_blind_numbers: [
+            0x000072b2, 0x00009e73, 0x00d55e00, 0x008a3a70, 0x009a6a00, 0x00404040, 0x00000000,
+            0x00808080,
+        ],
+        text_dark: 0x00000000,
+        text_wrong_flag: 0x00ff0000,
+        message_box: 0x00c0c0c0,
+        message_border: 0x00808080,
+        message_text: 0x00000000,
+        button: 0x00d4d0c8,
+        button_border: 0x00808080,
+        button_text: 0x00000000,
+        text_cursor: 0x00000080,
+        odds_safe: 0x00008000,
+        odds_mine: 0x00ff0000,
+        hint: 0x000000ff,
+        cursor: 0x00ff8000,
+        status_bar: 0x00c0c0c0,
+        counter: 0x00000000,
+        counter_text: 0x00ff0000,
+    };
+
+    /// White on black, with bright numbers.
+    pub const HIGH_CONTRAST: Self = Self {
+        oob: 0x00000000,
+        line: 0x00808080,
+        unopened: 0x00ffffff,
+        opened: 0x00000000,
+        text_light: 0x00ffffff,
+        numbers: [
+            0x0000ffff, 0x0000ff00, 0x00ff4040, 0x00ff00ff, 0x00ffff00, 0x00ffffff, 0x00ff8000,
+            0x00c0c0c0,
+        ],
+        color_blind_numbers: [
+            0x0056b4e9, 0x0030d0a0, 0x00ff8030, 0x00e090c0, 0x00f0e442, 0x00ffffff, 0x00e69f00,
+            0x00c0c0c0,
+        ],
+        text_dark: 0x00000000,
+        text_wrong_flag: 0x00ff0000,
+        message_box: 0x00000000,
+        message_border: 0x00ffffff,
+        message_text: 0x00ffffff,
+        button: 0x00000000,
+        button_border: 0x00ffffff,
+        button_text: 0x00ffffff,
+        text_cursor: 0x00ffff00,
+        odds_safe: 0x0000ff00,
+        odds_mine: 0x00ff0000,
+        hint: 0x0000ffff,
+        cursor: 0x00ffff00,
+        status_bar: 0x00000000,
+        counter: 0x00000000,
+        counter_text: 0x00ff0000,
+    };
+
+    /// The built-in themes, by their names in theme files.
+    pub const BUILT_IN: [(&'static str, Self); 4] = [
+        ("light", Self::LIGHT),
+        ("dark", Self::DARK),
+        ("classic", Self::CLASSIC),
+        ("high_contrast", Self::HIGH_CONTRAST),
+    ];
+
+    /// For pressed buttons.
+    pub fn button_shade(&self) -> u32 {
+        let bytes = self.button.to_be_bytes();
+        u32::from_be_bytes([
+            0, // always zero
+            bytes[1] / 2,
+            bytes[2] / 2,
+            bytes[3] / 2,
+        ])
+    }
+
+    /// Loads the theme file, or the light theme if there isn't one (or it
+    /// can't be read).
+    pub fn load() -> Self {
+        match shared::read_data_file(FILE_NAME) {
+            Ok(Some(s)) => Self::parse(&s).unwrap_or_else(|err| {
+                eprintln!("Couldn't read the theme: {err}");
+                Self::LIGHT
+            }),
+            Ok(None) => Self::LIGHT,
+            Err(err) => {
+                eprintln!("Couldn't read the theme: {err}");
+                Self::LIGHT
+            }
+        }
+    }
+
+    fn parse(s: &str) -> io::Result<Self> {
+        let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+        let mut lines = s.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(invalid("not a theme file".to_owned()));
+        }
+        let pairs = lines
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                line.split_once('=')
+                    .map(|(name, value)| (name.trim(), value.trim()))
+                    .ok_or_else(|| invalid(format!("bad line '{line}'")))
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let mut theme = match pairs.iter().find(|&&(name, _)| name == "base") {
+            Some(&(_, base)) => Self::BUILT_IN
+                .iter()
+                .find(|&&(name, _)| name == base)
+                .map(|&(_, theme)| theme)
+                .ok_or_else(|| invalid(format!("there's no '{base}' theme")))?,
+            None => Self::LIGHT,
+        };
+        for (name, value) in pairs.into_iter().filter(|&(name, _)| name != "base") {
+            let color = theme
+                .color_mut(name)
+                .ok_or_else(|| invalid(format!("unknown colour '{name}'")))?;
+            let hex = value.trim_start_matches('#');
+            *color = match u32::from_str_radix(hex, 16) {
+                Ok(value) if hex.len() == 6 => value,
+                _ => return Err(invalid(format!("bad colour '{value}' for {name}"))),
+            };
+        }
+        Ok(theme)
+    }
+
+    fn color_mut(&mut self, name: &str) -> Option<&mut u32> {
+        // Counting from 1, like the numbers themselves.
+        let number = |prefix| {
+            name.strip_prefix(prefix)
+                .and_then(|n| n.parse::<usize>().ok())
+                .filter(|n| (1..=8).contains(n))
+                .map(|n| n - 1)
+        };
+        if let Some(i) = number("number_") {
+            return Some(&mut self.numbers[i]);
+        }
+        if let Some(i) = number("color_blind_number_") {
+            return Some(&mut self.color_blind_numbers[i]);
+        }
+        Some(match name {
+            "oob" => &mut self.oob,
+            "line" => &mut self.line,
+            "unopened" => &mut self.unopened,
+            "opened" => &mut self.opened,
+            "text_light" => &mut self.text_light,
+            "text_dark" => &mut self.text_dark,
+            "text_wrong_flag" => &mut self.text_wrong_flag,
+            "message_box" => &mut self.message_box,
+            "message_border" => &mut self.message_border,
+            "message_text" => &mut self.message_text,
+            "button" => &mut self.button,
+            "button_border" => &mut self.button_border,
+            "button_text" => &mut self.button_text,
+            "text_cursor" => &mut self.text_cursor,
+            "odds_safe" => &mut self.odds_safe,
+            "odds_mine" => &mut self.odds_mine,
+            "hint" => &mut self.hint,
+            "cursor" => &mut self.cursor,
+            "status_bar" => &mut self.status_bar,
+            "counter" => &mut self.counter,
+            "counter_text" => &mut self.counter_text,
+            _ => return None,
+        })
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::LIGHT
+    }
+}
+
+#[test]
+fn test_theme_file() {
+    let theme = Theme::parse(&format!(
+        "{HEADER}\nbase = dark\nhint = #123456\nnumber_8 = abcdef\n"
+    ))
+    .unwrap();
+    assert_eq!(theme.hint, 0x00123456);
+    assert_eq!(theme.numbers[7], 0x00abcdef);
+    assert_eq!(theme.opened, Theme::DARK.opened);
+    assert_eq!(Theme::parse(HEADER).unwrap(), Theme::LIGHT);
+
+    assert!(Theme::parse(&format!("{HEADER}\nbase = neon\n")).is_err());
+    assert!(Theme::parse(&format!("{HEADER}\nglow = 000000\n")).is_err());
+    assert!(Theme::parse(&format!("{HEADER}\nline = 12345\n")).is_err());
+    assert!(Theme::parse(&format!("{HEADER}\nnumber_9 = 000000\n")).is_err());
+}
@@ -0,0 +1,234 @@
+//! Plays in a terminal, for when there's no window to open (like over SSH).
+//!
+//! Draws with ANSI escape codes and box drawing characters, and reads keys
+//! one at a time by putting the terminal in raw mode with `stty`, so it only
+//! works where `stty` does.
+
+use std::{
+    io::{self, Read, Write},
+    process::{Command, Stdio},
+};
+
+use crate::board::{self, Board, Cell, GameState};
+use crate::shared::{Config, Stopwatch};
+
+/// ANSI colours for the numbers 1 to 8, roughly the classic ones.
+const NUMBER_COLORS: [&str; 8] = ["94", "32", "91", "34", "31", "36", "35", "90"];
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Input {
+    Move(isize, isize),
+    /// Chords if the cell is already open.
+    Open,
+    Flag,
+    Chord,
+    Undo,
+    Redo,
+    NewGame,
+    Quit,
+}
+
+/// Plays games in the terminal until the player quits.
+pub fn run(cfg: &Config) -> io::Result<()> {
+    let _raw_mode = RawMode::enable()?;
+    let mut keys = io::stdin().lock().bytes().map_while(Result::ok);
+    let mut stdout = io::stdout().lock();
+
+    let mut board = board::new_game(cfg);
+    let mut cursor = (board.cols() / 2, board.rows() / 2);
+    let mut stopwatch = Stopwatch::default();
+    loop {
+        if board.has_started() && !board.is_game_over() {
+            stopwatch.start();
+        } else {
+            stopwatch.stop();
+        }
+        write!(stdout, "{}", render(cfg, &board, cursor, &stopwatch))?;
+        stdout.flush()?;
+
+        let Some(input) = read_input(&mut keys) else {
+            break;
+        };
+        let (x, y) = cursor;
+        match input {
+            Input::Move(dx, dy) => {
+                cursor = (
+                    x.saturating_add_signed(dx).min(board.cols() - 1),
+                    y.saturating_add_signed(dy).min(board.rows() - 1),
+                );
+            }
+            Input::Open if board.cell(x, y) == Cell::Opened => _ = board.chord(x, y),
+            Input::Open => _ = board.open(x, y),
+            Input::Flag => _ = board.toggle_flag(x, y),
+            Input::Chord => _ = board.chord(x, y),
+            Input::Undo => _ = board.undo(),
+            Input::Redo => _ = board.redo(),
+            Input::NewGame => {
+                board = board::new_game(cfg);
+                stopwatch = Stopwatch::default();
+            }
+            Input::Quit => break,
+        }
+    }
+    write!(stdout, "\r\n")?;
+    Ok(())
+}
+
+/// Waits for a key the game knows about. Returns `None` once there aren't any
+/// more keys.
+fn read_input(keys: &mut impl Iterator<Item = u8>) -> Option<Input> {
+    loop {
+        let input = match keys.next()? {
+            // Arrow keys are ESC [ A through D.
+            0x1b => {
+                if keys.next()? != b'[' {
+                    continue;
+                }
+                match keys.next()? {
+                    b'A' => Input::Move(0, -1),
+                    b'B' => Input::Move(0, 1),
+                    b'C' => Input::Move(1, 0),
+                    b'D' => Input::Move(-1, 0),
+                    _ => continue,
+                }
+            }
+            b'k' => Input::Move(0, -1),
+            b'j' => Input::Move(0, 1),
+            b'l' => Input::Move(1, 0),
+            b'h' => Input::Move(-1, 0),
+            b' ' | b'\r' | b'\n' => Input::Open,
+            b'f' => Input::Flag,
+            b'c' => Input::Chord,
+            b'u' => Input::Undo,
+            b'r' => Input::Redo,
+            b'n' => Input::NewGame,
+            // Ctrl+C doesn't stop the program in raw mode.
+            b'q' | 0x03 => Input::Quit,
+            _ => continue,
+        };
+        return Some(input);
+    }
+}
+
+/// Draws the whole screen, from the top left.
+fn render(cfg: &Config, board: &Board, cursor: (usize, usize), stopwatch: &Stopwatch) -> String {
+    // Raw mode needs \r for new lines.
+    let mut s = String::from("\x1b[H\x1b[2J");
+    s += &format!(
+        "{}  {}{}  {}{}\r\n",
+        cfg.en_jp("Minesweeper", "マインスイーパ"),
+        cfg.en_jp("Mines: ", "地雷："),
+        board.mines_left(),
+        cfg.en_jp("Time: ", "タイム："),
+        stopwatch.elapsed().as_secs(),
+    );
+
+    let line = "─".repeat(board.cols() * 2 + 1);
+    s += &format!("┌{line}┐\r\n");
+    for y in 0..board.rows() {
+        s += "│";
+        for x in 0..board.cols() {
+            let cell = cell_text(board, x, y);
+            if (x, y) == cursor {
+                s += &format!(" \x1b[7m{cell}\x1b[27m");
+            } else {
+                s += &format!(" {cell}");
+            }
+        }
+        s += " │\r\n";
+    }
+    s += &format!("└{line}┘\r\n");
+
+    s += match board.state() {
+        GameState::InProgress => cfg.en_jp(
+            "Arrows/hjkl: move  Space: open  f: flag  c: chord\r\n\
+             u: undo  r: redo  n: new game  q: quit",
+            "矢印/hjkl：移動　スペース：開く　f：旗　c：まとめて開く\r\n\
+             u：元に戻す　r：やり直す　n：新しいゲーム　q：やめる",
+        ),
+        GameState::Won => cfg.en_jp(
+            "You won!  n: new game  q: quit",
+            "やった！　n：新しいゲーム　q：やめる",
+        ),
+        GameState::Lost => cfg.en_jp(
+            "You lost!  u: undo  n: new game  q: quit",
+            "負けました。　u：元に戻す　n：新しいゲーム　q：やめる",
+        ),
+    };
+    if board.may_need_guess() && !board.is_game_over() {
+        s += cfg.en_jp(
+            "\r\nNo layout that never needs a guess turned up, so this one might.",
+            "\r\n運任せなしの配置が見つからなかったので、推測が必要になるかもしれません。",
+        );
+    }
+    s
+}
+
+/// A cell as one coloured character. Mines show once the game is over.
+fn cell_text(board: &Board, x: usize, y: usize) -> String {
+    let is_mine = board.is_mine(x, y);
+    let game_over = board.is_game_over();
+    match board.cell(x, y) {
+        Cell::Opened if is_mine => "\x1b[1;31m*\x1b[0m".to_owned(),
+        Cell::Opened => match board.nearby_mines(x, y) {
+            0 => " ".to_owned(),
+            n => format!("\x1b[{}m{n}\x1b[0m", NUMBER_COLORS[n as usize - 1]),
+        },
+        Cell::Flagged if game_over && !is_mine => "\x1b[31mx\x1b[0m".to_owned(),
+        Cell::Flagged => "\x1b[1;31mF\x1b[0m".to_owned(),
+        Cell::Questioned if !game_over => "\x1b[1;33m?\x1b[0m".to_owned(),
+        Cell::Unopened | Cell::Questioned if game_over && is_mine => "*".to_owned(),
+        Cell::Unopened | Cell::Questioned => "\x1b[90m·\x1b[0m".to_owned(),
+    }
+}
+
+/// Puts the terminal in raw mode, and back how it was when dropped.
+struct RawMode {
+    /// From `stty -g`.
+    old_settings: String,
+}
+
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        let output = stty(&["-g"])?;
+        let old_settings = String::from_utf8_lossy(&output).trim().to_owned();
+        stty(&["raw", "-echo"])?;
+        // Hide the terminal's own cursor too.
+        print!("\x1b[?25l");
+        Ok(Self { old_settings })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        print!("\x1b[?25h");
+        if let Err(err) = stty(&[&self.old_settings]) {
+            eprintln!("Couldn't reset the terminal: {err}");
+        }
+    }
+}
+
+fn stty(args: &[&str]) -> io::Result<Vec<u8>> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(
+            "stty failed (is this running in a terminal?)",
+        ));
+    }
+    Ok(output.stdout)
+}
+
+#[test]
+fn test_read_input() {
+    let mut keys = b"\x1b[Axq\x1b[D f".iter().copied();
+    assert_eq!(read_input(&mut keys), Some(Input::Move(0, -1)));
+    // Unknown keys are skipped.
+    assert_eq!(read_input(&mut keys), Some(Input::Quit));
+    assert_eq!(read_input(&mut keys), Some(Input::Move(-1, 0)));
+    assert_eq!(read_input(&mut keys), Some(Input::Open));
+    assert_eq!(read_input(&mut keys), Some(Input::Flag));
+    assert_eq!(read_input(&mut keys), None);
+}